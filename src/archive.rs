@@ -0,0 +1,500 @@
+use crate::error::{EXIT_SUCCESS, Error};
+use crate::export::convert;
+use crate::{Args, StripOptions, TreeSitterLanguage, parse_language, walk};
+use flate2::Compression;
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use std::fs::File;
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
+use zip::write::SimpleFileOptions;
+use zip::{DateTime, ZipArchive, ZipWriter};
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ArchiveFormat {
+    Tar,
+    TarGz,
+    Zip,
+}
+impl ArchiveFormat {
+    pub fn detect(path: &str) -> Option<ArchiveFormat> {
+        let name = path.to_lowercase();
+        if name.ends_with(".tar") {
+            Some(ArchiveFormat::Tar)
+        } else if [".tar.gz", ".tgz", ".crate"]
+            .iter()
+            .any(|ext| name.ends_with(ext))
+        {
+            Some(ArchiveFormat::TarGz)
+        } else if name.ends_with(".zip") {
+            Some(ArchiveFormat::Zip)
+        } else {
+            None
+        }
+    }
+}
+#[derive(clap::Args, Debug)]
+pub struct ArchiveArgs {
+    #[arg(value_name = "ARCHIVE")]
+    pub input: String,
+    #[arg(long, value_name = "ARCHIVE")]
+    pub out: String,
+    #[arg(long)]
+    pub include_generated: bool,
+}
+pub enum ArchiveWriter {
+    Tar(tar::Builder<File>),
+    TarGz(Box<tar::Builder<GzEncoder<File>>>),
+    Zip(Box<ZipWriter<File>>),
+}
+fn zip_error(e: zip::result::ZipError) -> Error {
+    Error::Io(io::Error::other(e))
+}
+fn zip_time(mtime: u64) -> DateTime {
+    let days = (mtime / 86400) as i64 + 719468;
+    let secs = mtime % 86400;
+    let era = days.div_euclid(146097);
+    let doe = days - era * 146097;
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u8;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u8;
+    let year = (yoe + era * 400 + i64::from(month <= 2)) as u16;
+    DateTime::from_date_and_time(
+        year,
+        month,
+        day,
+        (secs / 3600) as u8,
+        (secs / 60 % 60) as u8,
+        (secs % 60) as u8,
+    )
+    .unwrap_or_default()
+}
+fn unix_time(time: DateTime) -> u64 {
+    let (month, day) = (i64::from(time.month()), i64::from(time.day()));
+    let year = i64::from(time.year()) - i64::from(month <= 2);
+    let era = year.div_euclid(400);
+    let yoe = year - era * 400;
+    let doy = (153 * (month + if month > 2 { -3 } else { 9 }) + 2) / 5 + day - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    let days = era * 146097 + doe - 719468;
+    let secs =
+        i64::from(time.hour()) * 3600 + i64::from(time.minute()) * 60 + i64::from(time.second());
+    (days * 86400 + secs).max(0) as u64
+}
+fn tar_input(input: File, format: ArchiveFormat) -> tar::Archive<Box<dyn Read>> {
+    match format {
+        ArchiveFormat::TarGz => tar::Archive::new(Box::new(GzDecoder::new(input))),
+        _ => tar::Archive::new(Box::new(input)),
+    }
+}
+fn canonical_target(path: &str) -> Option<PathBuf> {
+    let path = Path::new(path);
+    let parent = match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => Path::new("."),
+    };
+    Some(parent.canonicalize().ok()?.join(path.file_name()?))
+}
+fn tar_file_header(size: usize, mode: u32, mtime: u64) -> tar::Header {
+    let mut header = tar::Header::new_gnu();
+    header.set_size(size as u64);
+    header.set_mode(mode);
+    header.set_mtime(mtime);
+    header
+}
+fn tar_dir_header(mode: u32, mtime: u64) -> tar::Header {
+    let mut header = tar::Header::new_gnu();
+    header.set_entry_type(tar::EntryType::Directory);
+    header.set_size(0);
+    header.set_mode(mode);
+    header.set_mtime(mtime);
+    header
+}
+fn tar_link_header(mtime: u64) -> tar::Header {
+    let mut header = tar::Header::new_gnu();
+    header.set_entry_type(tar::EntryType::Symlink);
+    header.set_size(0);
+    header.set_mode(0o777);
+    header.set_mtime(mtime);
+    header
+}
+impl ArchiveWriter {
+    pub fn create(path: &str, format: ArchiveFormat) -> Result<ArchiveWriter, Error> {
+        let file = File::create(path).map_err(|e| Error::from(e).in_file(path))?;
+        Ok(match format {
+            ArchiveFormat::Tar => ArchiveWriter::Tar(tar::Builder::new(file)),
+            ArchiveFormat::TarGz => ArchiveWriter::TarGz(Box::new(tar::Builder::new(
+                GzEncoder::new(file, Compression::default()),
+            ))),
+            ArchiveFormat::Zip => ArchiveWriter::Zip(Box::new(ZipWriter::new(file))),
+        })
+    }
+    pub fn add_file(
+        &mut self,
+        path: &str,
+        data: &[u8],
+        mode: u32,
+        mtime: u64,
+    ) -> Result<(), Error> {
+        match self {
+            ArchiveWriter::Tar(builder) => {
+                builder.append_data(&mut tar_file_header(data.len(), mode, mtime), path, data)?
+            }
+            ArchiveWriter::TarGz(builder) => {
+                builder.append_data(&mut tar_file_header(data.len(), mode, mtime), path, data)?
+            }
+            ArchiveWriter::Zip(writer) => {
+                let options = SimpleFileOptions::default()
+                    .unix_permissions(mode)
+                    .last_modified_time(zip_time(mtime));
+                writer.start_file(path, options).map_err(zip_error)?;
+                writer.write_all(data)?;
+            }
+        }
+        Ok(())
+    }
+    pub fn add_directory(&mut self, path: &str, mode: u32, mtime: u64) -> Result<(), Error> {
+        match self {
+            ArchiveWriter::Tar(builder) => {
+                builder.append_data(&mut tar_dir_header(mode, mtime), path, io::empty())?
+            }
+            ArchiveWriter::TarGz(builder) => {
+                builder.append_data(&mut tar_dir_header(mode, mtime), path, io::empty())?
+            }
+            ArchiveWriter::Zip(writer) => {
+                let options = SimpleFileOptions::default()
+                    .unix_permissions(mode)
+                    .last_modified_time(zip_time(mtime));
+                writer.add_directory(path, options).map_err(zip_error)?;
+            }
+        }
+        Ok(())
+    }
+    pub fn add_symlink(&mut self, path: &str, target: &str, mtime: u64) -> Result<(), Error> {
+        match self {
+            ArchiveWriter::Tar(builder) => {
+                builder.append_link(&mut tar_link_header(mtime), path, target)?
+            }
+            ArchiveWriter::TarGz(builder) => {
+                builder.append_link(&mut tar_link_header(mtime), path, target)?
+            }
+            ArchiveWriter::Zip(writer) => {
+                let options = SimpleFileOptions::default().last_modified_time(zip_time(mtime));
+                writer
+                    .add_symlink(path, target, options)
+                    .map_err(zip_error)?;
+            }
+        }
+        Ok(())
+    }
+    pub fn finish(self) -> Result<(), Error> {
+        match self {
+            ArchiveWriter::Tar(builder) => {
+                builder.into_inner()?;
+            }
+            ArchiveWriter::TarGz(builder) => {
+                builder.into_inner()?.finish()?;
+            }
+            ArchiveWriter::Zip(writer) => {
+                writer.finish().map_err(zip_error)?;
+            }
+        }
+        Ok(())
+    }
+}
+struct Rewriter<'a> {
+    cli: &'a Args,
+    options: StripOptions,
+    language: Option<TreeSitterLanguage>,
+    include_generated: bool,
+    first_failure: Option<i32>,
+    stripped: usize,
+    copied: usize,
+}
+impl Rewriter<'_> {
+    fn strip(&mut self, path: &str, data: &[u8]) -> Result<Option<String>, Error> {
+        match convert(
+            path,
+            data,
+            self.language,
+            &self.options,
+            self.include_generated,
+        ) {
+            Ok(Some(text)) => {
+                self.stripped += 1;
+                Ok(Some(text))
+            }
+            Ok(None) => {
+                self.copied += 1;
+                Ok(None)
+            }
+            Err(e) => {
+                let e = e.in_file(path);
+                if !self.cli.force {
+                    return Err(e);
+                }
+                e.report(self.cli.error_format);
+                self.first_failure.get_or_insert(e.exit_code());
+                self.copied += 1;
+                Ok(None)
+            }
+        }
+    }
+    fn tar<R: Read, W: Write>(
+        &mut self,
+        mut input: tar::Archive<R>,
+        output: &mut tar::Builder<W>,
+    ) -> Result<(), Error> {
+        for entry in input.entries()? {
+            let mut entry = entry?;
+            let path = entry.path()?.to_string_lossy().into_owned();
+            let mut header = entry.header().clone();
+            let mut data = Vec::new();
+            entry.read_to_end(&mut data)?;
+            let entry_type = header.entry_type();
+            if entry_type.is_symlink() || entry_type.is_hard_link() {
+                let target = entry
+                    .link_name()?
+                    .map(|p| p.to_string_lossy().into_owned())
+                    .unwrap_or_default();
+                output.append_link(&mut header, &path, target)?;
+                self.copied += 1;
+                continue;
+            }
+            if entry_type.is_file()
+                && let Some(text) = self.strip(&path, &data)?
+            {
+                data = text.into_bytes();
+                header.set_size(data.len() as u64);
+            } else if !entry_type.is_file() {
+                self.copied += 1;
+            }
+            output.append_data(&mut header, &path, data.as_slice())?;
+        }
+        Ok(())
+    }
+    fn zip(
+        &mut self,
+        mut input: ZipArchive<File>,
+        output: &mut ZipWriter<File>,
+    ) -> Result<(), Error> {
+        for index in 0..input.len() {
+            let raw = input.by_index_raw(index).map_err(zip_error)?;
+            let path = raw.name().map_err(zip_error)?.into_owned();
+            if raw.is_dir() || raw.is_symlink() || !walk::is_wanted(&path, self.language) {
+                output.raw_copy_file(raw).map_err(zip_error)?;
+                self.copied += 1;
+                continue;
+            }
+            drop(raw);
+            let mut entry = input.by_index(index).map_err(zip_error)?;
+            let mut data = Vec::new();
+            entry.read_to_end(&mut data)?;
+            let mut options = SimpleFileOptions::default()
+                .compression_method(entry.compression())
+                .last_modified_time(entry.last_modified().unwrap_or_default());
+            if let Some(mode) = entry.unix_mode() {
+                options = options.unix_permissions(mode);
+            }
+            drop(entry);
+            match self.strip(&path, &data)? {
+                Some(text) => {
+                    output.start_file(path, options).map_err(zip_error)?;
+                    output.write_all(text.as_bytes())?;
+                }
+                None => {
+                    let raw = input.by_index_raw(index).map_err(zip_error)?;
+                    output.raw_copy_file(raw).map_err(zip_error)?;
+                }
+            }
+        }
+        Ok(())
+    }
+    fn tar_entries<R: Read>(
+        &mut self,
+        mut input: tar::Archive<R>,
+        output: &mut ArchiveWriter,
+    ) -> Result<(), Error> {
+        for entry in input.entries()? {
+            let mut entry = entry?;
+            let path = entry.path()?.to_string_lossy().into_owned();
+            let header = entry.header();
+            let (entry_type, mtime) = (header.entry_type(), header.mtime().unwrap_or(0));
+            let mode = header.mode().unwrap_or(0o644) & 0o7777;
+            if entry_type.is_symlink() {
+                let target = entry
+                    .link_name()?
+                    .map(|p| p.to_string_lossy().into_owned())
+                    .unwrap_or_default();
+                output.add_symlink(&path, &target, mtime)?;
+                self.copied += 1;
+            } else if entry_type.is_dir() {
+                output.add_directory(&path, mode, mtime)?;
+                self.copied += 1;
+            } else if entry_type.is_file() {
+                let mut data = Vec::new();
+                entry.read_to_end(&mut data)?;
+                if let Some(text) = self.strip(&path, &data)? {
+                    data = text.into_bytes();
+                }
+                output.add_file(&path, &data, mode, mtime)?;
+            } else {
+                return Err(Error::Usage(format!(
+                    "{path}: {entry_type:?} entries cannot be converted to another archive format"
+                )));
+            }
+        }
+        Ok(())
+    }
+    fn zip_entries(
+        &mut self,
+        mut input: ZipArchive<File>,
+        output: &mut ArchiveWriter,
+    ) -> Result<(), Error> {
+        for index in 0..input.len() {
+            let mut entry = input.by_index(index).map_err(zip_error)?;
+            let path = entry.name().map_err(zip_error)?.into_owned();
+            let mtime = entry.last_modified().map_or(0, unix_time);
+            let mode = entry.unix_mode().map(|mode| mode & 0o7777);
+            let (is_dir, is_symlink) = (entry.is_dir(), entry.is_symlink());
+            let mut data = Vec::new();
+            entry.read_to_end(&mut data)?;
+            drop(entry);
+            if is_dir {
+                output.add_directory(&path, mode.unwrap_or(0o755), mtime)?;
+                self.copied += 1;
+            } else if is_symlink {
+                output.add_symlink(&path, &String::from_utf8_lossy(&data), mtime)?;
+                self.copied += 1;
+            } else {
+                if let Some(text) = self.strip(&path, &data)? {
+                    data = text.into_bytes();
+                }
+                output.add_file(&path, &data, mode.unwrap_or(0o644), mtime)?;
+            }
+        }
+        Ok(())
+    }
+}
+pub fn run(args: &ArchiveArgs, cli: &Args) -> Result<i32, Error> {
+    let detect = |path: &str| {
+        ArchiveFormat::detect(path).ok_or_else(|| {
+            Error::Usage(format!(
+                "'{path}' is not a .tar, .tar.gz, .tgz, .crate or .zip archive"
+            ))
+        })
+    };
+    let (format, out_format) = (detect(&args.input)?, detect(&args.out)?);
+    let input = File::open(&args.input).map_err(|e| Error::from(e).in_file(&args.input))?;
+    if canonical_target(&args.input).is_some_and(|p| Some(p) == canonical_target(&args.out)) {
+        return Err(Error::Usage(format!(
+            "--out '{}' is the input archive; write to a different path",
+            args.out
+        )));
+    }
+    let mut rewriter = Rewriter {
+        cli,
+        options: StripOptions::new(cli)?,
+        language: cli.language.as_deref().map(parse_language).transpose()?,
+        include_generated: args.include_generated,
+        first_failure: None,
+        stripped: 0,
+        copied: 0,
+    };
+    let result = match (format, ArchiveWriter::create(&args.out, out_format)?) {
+        (ArchiveFormat::Zip, ArchiveWriter::Zip(mut writer)) => ZipArchive::new(input)
+            .map_err(zip_error)
+            .and_then(|archive| rewriter.zip(archive, &mut writer))
+            .and_then(|_| ArchiveWriter::Zip(writer).finish()),
+        (ArchiveFormat::Zip, mut writer) => ZipArchive::new(input)
+            .map_err(zip_error)
+            .and_then(|archive| rewriter.zip_entries(archive, &mut writer))
+            .and_then(|_| writer.finish()),
+        (format, ArchiveWriter::Tar(mut builder)) => rewriter
+            .tar(tar_input(input, format), &mut builder)
+            .and_then(|_| ArchiveWriter::Tar(builder).finish()),
+        (format, ArchiveWriter::TarGz(mut builder)) => rewriter
+            .tar(tar_input(input, format), &mut builder)
+            .and_then(|_| ArchiveWriter::TarGz(builder).finish()),
+        (format, mut writer) => rewriter
+            .tar_entries(tar_input(input, format), &mut writer)
+            .and_then(|_| writer.finish()),
+    };
+    result.map_err(|e| e.in_file(&args.input))?;
+    eprintln!(
+        "Wrote {}: {} stripped, {} copied verbatim",
+        args.out, rewriter.stripped, rewriter.copied
+    );
+    Ok(rewriter.first_failure.unwrap_or(EXIT_SUCCESS))
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn test_detect_format() {
+        assert_eq!(ArchiveFormat::detect("a.tar"), Some(ArchiveFormat::Tar));
+        assert_eq!(
+            ArchiveFormat::detect("serde-1.0.0.crate"),
+            Some(ArchiveFormat::TarGz)
+        );
+        assert_eq!(
+            ArchiveFormat::detect("pkg-1.0.TGZ"),
+            Some(ArchiveFormat::TarGz)
+        );
+        assert_eq!(ArchiveFormat::detect("pkg.zip"), Some(ArchiveFormat::Zip));
+        assert_eq!(ArchiveFormat::detect("pkg.tar.bz2"), None);
+    }
+    #[test]
+    fn test_zip_time() {
+        assert_eq!(
+            zip_time(1_700_000_000),
+            DateTime::from_date_and_time(2023, 11, 14, 22, 13, 20).unwrap()
+        );
+    }
+    #[test]
+    fn test_unix_time() {
+        assert_eq!(unix_time(zip_time(1_700_000_000)), 1_700_000_000);
+        assert_eq!(unix_time(zip_time(951_782_400)), 951_782_400);
+    }
+    #[test]
+    #[cfg(feature = "python")]
+    fn test_tar_to_zip() {
+        use clap::Parser;
+        let root = std::env::temp_dir().join(format!("rmcm-archive-{}", std::process::id()));
+        std::fs::create_dir_all(&root).unwrap();
+        let (input, out) = (root.join("pkg.tar"), root.join("pkg.zip"));
+        let mut writer =
+            ArchiveWriter::create(input.to_str().unwrap(), ArchiveFormat::Tar).unwrap();
+        writer.add_directory("pkg", 0o755, 1_700_000_000).unwrap();
+        writer
+            .add_file("pkg/a.py", b"x = 1  # c\n", 0o644, 1_700_000_000)
+            .unwrap();
+        writer.finish().unwrap();
+        let cli = Args::parse_from(["rmcm", "--no-config", "x"]);
+        let args = ArchiveArgs {
+            input: input.to_string_lossy().into_owned(),
+            out: out.to_string_lossy().into_owned(),
+            include_generated: false,
+        };
+        assert_eq!(run(&args, &cli).unwrap(), EXIT_SUCCESS);
+        let same = ArchiveArgs {
+            out: args.input.clone(),
+            ..args
+        };
+        let refused = run(&same, &cli);
+        let mut zip = ZipArchive::new(File::open(&out).unwrap()).unwrap();
+        assert!(zip.by_name("pkg/").unwrap().is_dir());
+        let mut entry = zip.by_name("pkg/a.py").unwrap();
+        let mut text = String::new();
+        entry.read_to_string(&mut text).unwrap();
+        assert_eq!(entry.unix_mode().map(|mode| mode & 0o777), Some(0o644));
+        assert_eq!(entry.last_modified().map(unix_time), Some(1_700_000_000));
+        drop(entry);
+        let intact = std::fs::metadata(&input).unwrap().len() > 0;
+        std::fs::remove_dir_all(&root).unwrap();
+        assert_eq!(text, "x = 1  \n");
+        assert!(matches!(refused, Err(Error::Usage(_))));
+        assert!(intact);
+    }
+}
@@ -0,0 +1,52 @@
+use crate::error::Error;
+use crate::{Comment, TreeSitterLanguage, find_comments};
+use similar::{DiffTag, TextDiff};
+use std::collections::HashSet;
+pub fn added_lines(old: &str, new: &str) -> HashSet<usize> {
+    TextDiff::from_lines(old, new)
+        .ops()
+        .iter()
+        .map(|op| op.as_tag_tuple())
+        .filter(|(tag, _, _)| *tag != DiffTag::Equal)
+        .flat_map(|(_, _, new_range)| new_range)
+        .collect()
+}
+pub fn new_comments(
+    comments: Vec<Comment>,
+    input: &str,
+    old: &str,
+    language: TreeSitterLanguage,
+) -> Result<Vec<Comment>, Error> {
+    let added = added_lines(old, input);
+    let old_comments = find_comments(old, language)?;
+    let existing: HashSet<&str> = old_comments.iter().map(|c| c.text(old).trim()).collect();
+    Ok(comments
+        .into_iter()
+        .filter(|c| {
+            (c.start.row..=c.end.row).any(|row| added.contains(&row))
+                && !existing.contains(c.text(input).trim())
+        })
+        .collect())
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    #[cfg(feature = "python")]
+    fn test_only_added_comments_are_selected() {
+        let old = "x = 1  # old\ny = 2\n";
+        let new = "x = 3  # old\n# new\ny = 2  # also new\nz = 4\n";
+        let lang = TreeSitterLanguage::Python;
+        let comments = find_comments(new, lang).unwrap();
+        let selected = new_comments(comments, new, old, lang).unwrap();
+        let texts: Vec<&str> = selected.iter().map(|c| c.text(new)).collect();
+        assert_eq!(texts, ["# new", "# also new"]);
+    }
+    #[test]
+    fn test_added_lines() {
+        let added = added_lines("a\nb\nc\n", "a\nB\nc\nd\n");
+        let mut rows: Vec<usize> = added.into_iter().collect();
+        rows.sort();
+        assert_eq!(rows, [1, 3]);
+    }
+}
@@ -0,0 +1,131 @@
+use crate::TreeSitterLanguage;
+use std::io::{self, Write};
+#[derive(Debug, Clone, Copy, PartialEq, clap::ValueEnum)]
+pub enum BundleFormat {
+    Text,
+    Markdown,
+}
+pub struct Bundle {
+    format: BundleFormat,
+    header: String,
+    files: usize,
+    lines: usize,
+    bytes: usize,
+}
+fn fence_language(language: TreeSitterLanguage) -> &'static str {
+    match language.name() {
+        "c#" => "csharp",
+        "c++" => "cpp",
+        name => name,
+    }
+}
+fn fence(content: &str) -> String {
+    let mut longest = 0;
+    let mut run = 0;
+    for ch in content.chars() {
+        run = if ch == '`' { run + 1 } else { 0 };
+        longest = longest.max(run);
+    }
+    "`".repeat((longest + 1).max(3))
+}
+impl Bundle {
+    pub fn new(format: BundleFormat, header: Option<&str>) -> Bundle {
+        let default = match format {
+            BundleFormat::Text => "==> {path} ({language}) <==",
+            BundleFormat::Markdown => "### {path}",
+        };
+        Bundle {
+            format,
+            header: header.unwrap_or(default).to_string(),
+            files: 0,
+            lines: 0,
+            bytes: 0,
+        }
+    }
+    pub fn render(&mut self, path: &str, language: TreeSitterLanguage, content: &str) -> String {
+        let lines = content.lines().count();
+        self.files += 1;
+        self.lines += lines;
+        self.bytes += content.len();
+        let header = self
+            .header
+            .replace("{path}", path)
+            .replace("{language}", language.name())
+            .replace("{lines}", &lines.to_string())
+            .replace("{bytes}", &content.len().to_string());
+        let mut output = String::new();
+        if self.files > 1 {
+            output.push('\n');
+        }
+        output.push_str(&header);
+        output.push('\n');
+        let fence = fence(content);
+        if self.format == BundleFormat::Markdown {
+            output.push('\n');
+            output.push_str(&fence);
+            output.push_str(fence_language(language));
+            output.push('\n');
+        }
+        output.push_str(content);
+        if !content.is_empty() && !content.ends_with('\n') {
+            output.push('\n');
+        }
+        if self.format == BundleFormat::Markdown {
+            output.push_str(&fence);
+            output.push('\n');
+        }
+        output
+    }
+    pub fn write(
+        &mut self,
+        path: &str,
+        language: TreeSitterLanguage,
+        content: &str,
+    ) -> io::Result<()> {
+        let output = self.render(path, language, content);
+        let mut stdout = io::stdout();
+        stdout.write_all(output.as_bytes())?;
+        stdout.flush()
+    }
+    pub fn totals(&self) -> String {
+        let summary = format!(
+            "{} file(s), {} line(s), {} byte(s)",
+            self.files, self.lines, self.bytes
+        );
+        match self.format {
+            BundleFormat::Text => format!("\n==> total: {} <==\n", summary),
+            BundleFormat::Markdown => format!("\n**Total:** {}\n", summary),
+        }
+    }
+}
+#[cfg(all(test, any(feature = "rust-lang", feature = "python")))]
+mod tests {
+    use super::*;
+    #[test]
+    #[cfg(feature = "rust-lang")]
+    fn test_markdown_bundle() {
+        let mut bundle = Bundle::new(BundleFormat::Markdown, None);
+        let lang = TreeSitterLanguage::Rust;
+        assert_eq!(
+            bundle.render("src/a.rs", lang, "fn a() {}"),
+            "### src/a.rs\n\n```rust\nfn a() {}\n```\n"
+        );
+        assert_eq!(
+            bundle.render("src/b.rs", lang, "let s = \"```\";\n"),
+            "\n### src/b.rs\n\n````rust\nlet s = \"```\";\n````\n"
+        );
+        assert_eq!(
+            bundle.totals(),
+            "\n**Total:** 2 file(s), 2 line(s), 24 byte(s)\n"
+        );
+    }
+    #[test]
+    #[cfg(feature = "python")]
+    fn test_custom_header() {
+        let mut bundle = Bundle::new(BundleFormat::Text, Some("// {path}: {lines} lines"));
+        assert_eq!(
+            bundle.render("a.py", TreeSitterLanguage::Python, "x = 1\ny = 2\n"),
+            "// a.py: 2 lines\nx = 1\ny = 2\n"
+        );
+    }
+}
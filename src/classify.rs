@@ -0,0 +1,408 @@
+use std::ops::Range;
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CommentKind {
+    Line,
+    Block,
+    Doc,
+    Directive,
+    License,
+    Todo,
+    Empty,
+    #[value(alias = "separator")]
+    #[serde(alias = "separator")]
+    Banner,
+    Trailing,
+    Leading,
+}
+const ALL_KINDS: &[CommentKind] = &[
+    CommentKind::Line,
+    CommentKind::Block,
+    CommentKind::Doc,
+    CommentKind::Directive,
+    CommentKind::License,
+    CommentKind::Todo,
+    CommentKind::Empty,
+    CommentKind::Banner,
+    CommentKind::Trailing,
+    CommentKind::Leading,
+];
+impl CommentKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            CommentKind::Line => "line",
+            CommentKind::Block => "block",
+            CommentKind::Doc => "doc",
+            CommentKind::Directive => "directive",
+            CommentKind::License => "license",
+            CommentKind::Todo => "todo",
+            CommentKind::Empty => "empty",
+            CommentKind::Banner => "banner",
+            CommentKind::Trailing => "trailing",
+            CommentKind::Leading => "leading",
+        }
+    }
+}
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct CommentKinds(u16);
+impl CommentKinds {
+    pub fn contains(self, kind: CommentKind) -> bool {
+        self.0 & (1 << kind as u16) != 0
+    }
+    pub fn insert(&mut self, kind: CommentKind) {
+        self.0 |= 1 << kind as u16;
+    }
+    pub fn intersects(self, other: CommentKinds) -> bool {
+        self.0 & other.0 != 0
+    }
+    pub fn iter(self) -> impl Iterator<Item = CommentKind> {
+        ALL_KINDS.iter().copied().filter(move |k| self.contains(*k))
+    }
+    pub fn primary(self) -> CommentKind {
+        if self.contains(CommentKind::Doc) {
+            CommentKind::Doc
+        } else if self.contains(CommentKind::Block) {
+            CommentKind::Block
+        } else {
+            CommentKind::Line
+        }
+    }
+    pub fn names(self) -> Vec<&'static str> {
+        self.iter().map(|k| k.as_str()).collect()
+    }
+}
+impl FromIterator<CommentKind> for CommentKinds {
+    fn from_iter<I: IntoIterator<Item = CommentKind>>(iter: I) -> Self {
+        let mut kinds = CommentKinds::default();
+        for kind in iter {
+            kinds.insert(kind);
+        }
+        kinds
+    }
+}
+const DOC_PREFIXES: &[&str] = &[
+    "///", "//!", "/**", "/*!", "-- |", "--|", "-- ^", "{-|", "---",
+];
+const BLOCK_PREFIXES: &[&str] = &["/*", "{-", "<!--", "=begin", "--[[", "--[=", "\"\"\""];
+const BLOCK_DELIMITERS: &[(&str, &str)] = &[
+    ("/*", "*/"),
+    ("{-", "-}"),
+    ("<!--", "-->"),
+    ("--[[", "]]"),
+    ("=begin", "=end"),
+];
+const DIRECTIVES: &[&str] = &[
+    "eslint-disable",
+    "eslint-enable",
+    "eslint-env",
+    "jshint",
+    "@ts-ignore",
+    "@ts-expect-error",
+    "@ts-nocheck",
+    "@ts-check",
+    "@flow",
+    "@jsx",
+    "prettier-ignore",
+    "istanbul ignore",
+    "c8 ignore",
+    "webpackChunkName",
+    "<reference ",
+    "noqa",
+    "type: ignore",
+    "pylint:",
+    "mypy:",
+    "fmt: off",
+    "fmt: on",
+    "fmt: skip",
+    "isort:",
+    "pragma:",
+    "-*- coding",
+    "coding:",
+    "coding=",
+    "vim:",
+    "rubocop:",
+    "frozen_string_literal:",
+    "shellcheck ",
+    "NOLINT",
+    "NOSONAR",
+    "clang-format ",
+    "go:build",
+    "go:generate",
+    "go:embed",
+    "go:linkname",
+    "+build ",
+    "swiftlint:",
+    "phpcs:",
+    "@phpstan-",
+    "@formatter:",
+    "checkstyle:",
+    "rmcm:",
+];
+const INLINE_DIRECTIVES: &[&str] = &[
+    "eslint-disable",
+    "@ts-ignore",
+    "@ts-expect-error",
+    "prettier-ignore",
+    "noqa",
+    "type: ignore",
+    "pragma: no cover",
+    "pylint: disable",
+    "NOLINT",
+    "NOSONAR",
+    "rubocop:disable",
+    "-*- coding",
+];
+const LICENSE_MARKERS: &[&str] = &[
+    "copyright",
+    "spdx-license-identifier",
+    "licensed under",
+    "all rights reserved",
+    "\u{a9}",
+];
+const SEPARATOR_CHARS: &[char] = &['-', '=', '*', '#', '/', '~', '_', '+', '.', '<', '>', '|'];
+const LINE_MARKERS: &[&str] = &["///", "//!", "//", "#!", "#", "---", "--"];
+pub fn comment_body(text: &str) -> String {
+    let text = text.trim_end_matches(['\r', '\n']);
+    for (open, close) in BLOCK_DELIMITERS {
+        if text.len() >= open.len() + close.len() && text.starts_with(open) && text.ends_with(close)
+        {
+            let inner = &text[open.len()..text.len() - close.len()];
+            let inner = if *open == "/*" {
+                inner.strip_prefix(['*', '!']).unwrap_or(inner)
+            } else {
+                inner
+            };
+            let lines: Vec<&str> = inner.lines().collect();
+            let starred = lines.len() > 1
+                && lines[1..]
+                    .iter()
+                    .filter(|l| !l.trim().is_empty())
+                    .all(|l| l.trim_start().starts_with('*'));
+            return lines
+                .iter()
+                .enumerate()
+                .map(|(idx, line)| {
+                    if starred && idx > 0 {
+                        let line = line.trim_start();
+                        let line = line.strip_prefix('*').unwrap_or(line);
+                        line.strip_prefix(' ').unwrap_or(line)
+                    } else {
+                        line.strip_prefix(' ').unwrap_or(line)
+                    }
+                })
+                .collect::<Vec<_>>()
+                .join("\n");
+        }
+    }
+    text.lines()
+        .map(|line| {
+            let line = line.trim_start();
+            let line = LINE_MARKERS
+                .iter()
+                .find_map(|marker| line.strip_prefix(marker))
+                .unwrap_or(line);
+            line.strip_prefix(' ').unwrap_or(line)
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+pub fn body_spans(text: &str) -> Vec<Range<usize>> {
+    let trimmed_len = text.trim_end_matches(['\r', '\n']).len();
+    let trimmed = &text[..trimmed_len];
+    let line_spans = |range: Range<usize>, skip: &dyn Fn(usize, &str) -> usize| {
+        let mut spans = Vec::new();
+        let mut line_start = range.start;
+        for (idx, line) in text[range].split('\n').enumerate() {
+            let content = line.strip_suffix('\r').unwrap_or(line);
+            spans.push(line_start + skip(idx, content)..line_start + content.len());
+            line_start += line.len() + 1;
+        }
+        spans
+    };
+    for (open, close) in BLOCK_DELIMITERS {
+        if trimmed.len() >= open.len() + close.len()
+            && trimmed.starts_with(open)
+            && trimmed.ends_with(close)
+        {
+            let inner = open.len()..trimmed_len - close.len();
+            return line_spans(inner, &|idx, line| {
+                if idx == 0 {
+                    return 0;
+                }
+                let rest = line.trim_start();
+                line.len() - rest.strip_prefix('*').unwrap_or(rest).len()
+            });
+        }
+    }
+    line_spans(0..trimmed_len, &|_, line| {
+        let rest = line.trim_start();
+        let marker = LINE_MARKERS
+            .iter()
+            .find(|marker| rest.starts_with(*marker))
+            .map_or(0, |marker| marker.len());
+        line.len() - rest.len() + marker
+    })
+}
+fn is_global_directive(text: &str, body: &str) -> bool {
+    let Some(names) = ["global ", "globals ", "exported "]
+        .iter()
+        .find_map(|keyword| body.trim().strip_prefix(keyword))
+    else {
+        return false;
+    };
+    text.starts_with("/*")
+        && names.split(',').all(|entry| {
+            let (name, value) = entry.split_once(':').unwrap_or((entry, "true"));
+            let name = name.trim();
+            name.chars().next().is_some_and(|c| !c.is_ascii_digit())
+                && name
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '$')
+                && matches!(
+                    value.trim(),
+                    "true" | "false" | "readonly" | "writable" | "writeable" | "off"
+                )
+        })
+}
+pub fn classify(input: &str, range: Range<usize>, code_start: usize) -> CommentKinds {
+    let text = &input[range.clone()];
+    let body = comment_body(text);
+    let mut kinds = CommentKinds::default();
+    if BLOCK_PREFIXES.iter().any(|p| text.starts_with(p)) {
+        kinds.insert(CommentKind::Block);
+    } else {
+        kinds.insert(CommentKind::Line);
+    }
+    let is_doc = DOC_PREFIXES.iter().any(|p| text.starts_with(p))
+        && !text.starts_with("////")
+        && !text.starts_with("/**/")
+        && !text.starts_with("----");
+    if is_doc {
+        kinds.insert(CommentKind::Doc);
+    }
+    if (range.start == 0 && text.starts_with("#!"))
+        || text.starts_with("//go:")
+        || is_global_directive(text, &body)
+        || DIRECTIVES.iter().any(|d| body.trim_start().starts_with(d))
+        || INLINE_DIRECTIVES.iter().any(|d| text.contains(d))
+    {
+        kinds.insert(CommentKind::Directive);
+    }
+    let lower = text.to_lowercase();
+    if LICENSE_MARKERS.iter().any(|m| lower.contains(m))
+        || (range.start < code_start && lower.contains("license"))
+    {
+        kinds.insert(CommentKind::License);
+    }
+    if crate::todo::has_marker(text) {
+        kinds.insert(CommentKind::Todo);
+    }
+    if body.trim().is_empty() {
+        kinds.insert(CommentKind::Empty);
+    } else if !kinds.contains(CommentKind::Directive) && is_banner(&body) {
+        kinds.insert(CommentKind::Banner);
+    }
+    let line_start = input[..range.start].rfind('\n').map_or(0, |i| i + 1);
+    if input[line_start..range.start].trim().is_empty() {
+        kinds.insert(CommentKind::Leading);
+    } else {
+        kinds.insert(CommentKind::Trailing);
+    }
+    kinds
+}
+fn is_banner(body: &str) -> bool {
+    let run = |s: &str| {
+        s.chars()
+            .take_while(|c| SEPARATOR_CHARS.contains(c))
+            .count()
+    };
+    body.lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .all(|line| {
+            let leading = run(line);
+            let trailing = line
+                .chars()
+                .rev()
+                .take_while(|c| SEPARATOR_CHARS.contains(c))
+                .count();
+            leading >= 3 && (leading == line.chars().count() || trailing >= 3)
+        })
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    fn kinds_of(input: &str) -> Vec<&'static str> {
+        classify(input, 0..input.len(), usize::MAX).names()
+    }
+    #[test]
+    fn test_line_block_doc() {
+        assert_eq!(kinds_of("// hi"), ["line", "leading"]);
+        assert_eq!(kinds_of("/* hi */"), ["block", "leading"]);
+        assert_eq!(kinds_of("<!-- hi -->"), ["block", "leading"]);
+        assert_eq!(kinds_of("/// hi"), ["line", "doc", "leading"]);
+        assert_eq!(kinds_of("/** hi */"), ["block", "doc", "leading"]);
+        assert_eq!(kinds_of("{-| hi -}"), ["block", "doc", "leading"]);
+    }
+    #[test]
+    fn test_separator_lines_are_banners_not_doc() {
+        assert_eq!(kinds_of("////////"), ["line", "banner", "leading"]);
+        assert_eq!(kinds_of("/**/"), ["block", "empty", "leading"]);
+        assert_eq!(kinds_of("---------"), ["line", "banner", "leading"]);
+        assert_eq!(
+            kinds_of("# ===== Setup ====="),
+            ["line", "banner", "leading"]
+        );
+        assert_eq!(kinds_of("# == a note"), ["line", "leading"]);
+    }
+    #[test]
+    fn test_directive_license_todo() {
+        assert!(kinds_of("#!/bin/sh").contains(&"directive"));
+        assert!(kinds_of("// eslint-disable-next-line no-console").contains(&"directive"));
+        assert!(kinds_of("//go:generate stringer").contains(&"directive"));
+        assert!(kinds_of("// Copyright 2024 Acme").contains(&"license"));
+        assert!(kinds_of("// TODO(bob): x").contains(&"todo"));
+        assert!(!kinds_of("// go generate things").contains(&"directive"));
+    }
+    #[test]
+    fn test_global_directives_need_tool_syntax() {
+        assert!(kinds_of("/* global jQuery, $ */").contains(&"directive"));
+        assert!(kinds_of("/* globals window:readonly */").contains(&"directive"));
+        assert!(kinds_of("/* exported init */").contains(&"directive"));
+        assert!(!kinds_of("// global state is reset here").contains(&"directive"));
+        assert!(!kinds_of("// exported for tests").contains(&"directive"));
+        assert!(!kinds_of("// global foo").contains(&"directive"));
+        assert!(!kinds_of("/* global state is reset here */").contains(&"directive"));
+    }
+    #[test]
+    fn test_license_word_only_counts_in_header() {
+        let input = "// see LICENSE\nfn main() {} // see LICENSE";
+        let code_start = input.find("fn").unwrap();
+        assert!(classify(input, 0..14, code_start).contains(CommentKind::License));
+        let tail = input.rfind("//").unwrap();
+        let kinds = classify(input, tail..input.len(), code_start);
+        assert!(!kinds.contains(CommentKind::License));
+        assert!(kinds.contains(CommentKind::Trailing));
+    }
+    #[test]
+    fn test_comment_body_strips_markers() {
+        assert_eq!(comment_body("// x = 1;"), "x = 1;");
+        assert_eq!(comment_body("#    indented"), "   indented");
+        assert_eq!(comment_body("/* a */"), "a ");
+        assert_eq!(comment_body("/**\n * one\n * two\n */"), "\none\ntwo\n");
+        assert_eq!(comment_body("<!-- <div></div> -->"), "<div></div> ");
+        assert_eq!(comment_body("-- print(x)"), "print(x)");
+    }
+    #[test]
+    fn test_body_spans_exclude_delimiters() {
+        let spans = |text: &str| -> Vec<String> {
+            body_spans(text)
+                .into_iter()
+                .map(|r| text[r].to_string())
+                .collect()
+        };
+        assert_eq!(spans("// a b\n"), [" a b"]);
+        assert_eq!(spans("/* a\n * b\r\n */"), [" a", " b", ""]);
+        assert_eq!(spans("<!-- x -->"), [" x "]);
+    }
+}
@@ -0,0 +1,310 @@
+use crate::classify::{CommentKind, comment_body};
+use crate::error::Error;
+use crate::{Args, Comment, TreeSitterLanguage, find_comments, for_each_source, parse_source};
+use std::fs;
+use std::io::{self, Write};
+use tree_sitter::Node;
+#[derive(Debug, Clone, Copy, PartialEq, clap::ValueEnum)]
+pub enum CommentedCodeFormat {
+    Text,
+    Json,
+}
+#[derive(clap::Args, Debug)]
+pub struct CommentedCodeArgs {
+    #[arg(value_name = "PATHS")]
+    pub paths: Vec<String>,
+    #[arg(long, value_name = "SCORE", default_value_t = 0.6)]
+    pub threshold: f64,
+    #[arg(long)]
+    pub remove: bool,
+    #[arg(short, long, requires = "remove")]
+    pub in_place: bool,
+    #[arg(long, value_enum, value_name = "FORMAT", default_value = "text")]
+    pub format: CommentedCodeFormat,
+}
+#[derive(Debug, Clone)]
+pub struct Candidate {
+    pub comments: Vec<Comment>,
+    pub code: String,
+    pub confidence: f64,
+}
+pub fn run(args: &CommentedCodeArgs, cli: &Args) -> Result<i32, Error> {
+    let stdout = io::stdout();
+    let mut out = stdout.lock();
+    if args.remove
+        && !args.in_place
+        && (args.paths.len() > 1
+            || args
+                .paths
+                .iter()
+                .any(|p| fs::metadata(p).is_ok_and(|m| m.is_dir())))
+    {
+        return Err(Error::Usage(
+            "Cannot output multiple files to stdout without --in-place".to_string(),
+        ));
+    }
+    let mut records = Vec::new();
+    let code = for_each_source(&args.paths, cli, |path, language, input| {
+        let found: Vec<Candidate> = find_commented_code(input, language)?
+            .into_iter()
+            .filter(|c| c.confidence >= args.threshold)
+            .collect();
+        if args.remove {
+            let comments: Vec<Comment> = found.iter().flat_map(|c| c.comments.clone()).collect();
+            let output = crate::splice_comments(input, &comments);
+            if args.in_place {
+                if output != input {
+                    fs::write(path, output)?;
+                }
+            } else {
+                write!(out, "{}", output)?;
+                out.flush()?;
+            }
+            return Ok(());
+        }
+        for candidate in found {
+            let start = candidate.comments[0].start.row + 1;
+            let end = candidate.comments[candidate.comments.len() - 1].end.row + 1;
+            match args.format {
+                CommentedCodeFormat::Text => writeln!(
+                    out,
+                    "{}:{}-{}: {:.2}: {}",
+                    path,
+                    start,
+                    end,
+                    candidate.confidence,
+                    candidate.code.lines().next().unwrap_or("").trim()
+                )?,
+                CommentedCodeFormat::Json => records.push(serde_json::json!({
+                    "file": path,
+                    "start_line": start,
+                    "end_line": end,
+                    "confidence": candidate.confidence,
+                    "code": candidate.code,
+                })),
+            }
+        }
+        Ok(())
+    })?;
+    if args.format == CommentedCodeFormat::Json && !args.remove {
+        let output = serde_json::to_string_pretty(&records).map_err(io::Error::other)?;
+        writeln!(out, "{}", output)?;
+    }
+    Ok(code)
+}
+pub fn find_commented_code(
+    input: &str,
+    language: TreeSitterLanguage,
+) -> Result<Vec<Candidate>, Error> {
+    let mut candidates = Vec::new();
+    for group in group_comments(input, find_comments(input, language)?) {
+        let mut lo = 0;
+        let mut hi = group.len();
+        let mut best = block_confidence(input, &group, language)?;
+        loop {
+            let mut changed = false;
+            for from_start in [true, false] {
+                if hi - lo < 2 {
+                    break;
+                }
+                let edge = if from_start { lo } else { hi - 1 };
+                if block_confidence(input, &group[edge..edge + 1], language)? >= 0.5 {
+                    continue;
+                }
+                let rest = if from_start { lo + 1..hi } else { lo..hi - 1 };
+                let confidence = block_confidence(input, &group[rest.clone()], language)?;
+                if confidence >= best {
+                    (lo, hi, best) = (rest.start, rest.end, confidence);
+                    changed = true;
+                }
+            }
+            if !changed {
+                break;
+            }
+        }
+        let comments = group[lo..hi].to_vec();
+        candidates.push(Candidate {
+            code: block_code(input, &comments),
+            comments,
+            confidence: best,
+        });
+    }
+    Ok(candidates)
+}
+fn block_code(input: &str, comments: &[Comment]) -> String {
+    dedent(
+        &comments
+            .iter()
+            .map(|c| comment_body(c.text(input)))
+            .collect::<Vec<_>>()
+            .join("\n"),
+    )
+}
+fn block_confidence(
+    input: &str,
+    comments: &[Comment],
+    language: TreeSitterLanguage,
+) -> Result<f64, Error> {
+    code_confidence(&block_code(input, comments), language)
+}
+fn group_comments(input: &str, comments: Vec<Comment>) -> Vec<Vec<Comment>> {
+    let mut groups: Vec<Vec<Comment>> = Vec::new();
+    let mut prev_joinable = false;
+    for comment in comments {
+        if comment.kinds.contains(CommentKind::Doc) {
+            prev_joinable = false;
+            continue;
+        }
+        let joinable = comment.kinds.contains(CommentKind::Line)
+            && comment.kinds.contains(CommentKind::Leading);
+        let joins = joinable
+            && prev_joinable
+            && groups.last().and_then(|g| g.last()).is_some_and(|prev| {
+                let gap = &input[prev.range.end..comment.range.start];
+                gap.trim().is_empty() && gap.matches('\n').count() <= 1
+            });
+        match groups.last_mut() {
+            Some(group) if joins => group.push(comment),
+            _ => groups.push(vec![comment]),
+        }
+        prev_joinable = joinable;
+    }
+    groups
+}
+fn dedent(text: &str) -> String {
+    let indent = text
+        .lines()
+        .filter(|l| !l.trim().is_empty())
+        .map(|l| l.len() - l.trim_start().len())
+        .min()
+        .unwrap_or(0);
+    text.lines()
+        .map(|l| l.get(indent..).unwrap_or("").trim_end())
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+fn wrappers(language: TreeSitterLanguage) -> &'static [(&'static str, &'static str)] {
+    match language {
+        #[cfg(feature = "c")]
+        TreeSitterLanguage::C => &[("", ""), ("void f(void) {\n", "\n}")],
+        #[cfg(feature = "cpp")]
+        TreeSitterLanguage::Cpp => &[("", ""), ("void f() {\n", "\n}")],
+        #[cfg(feature = "rust-lang")]
+        TreeSitterLanguage::Rust => &[("", ""), ("fn f() {\n", "\n}")],
+        #[cfg(feature = "go")]
+        TreeSitterLanguage::Go => &[("package p\n", ""), ("package p\nfunc f() {\n", "\n}")],
+        #[cfg(feature = "java")]
+        TreeSitterLanguage::Java => &[
+            ("", ""),
+            ("class C {\n", "\n}"),
+            ("class C { void f() {\n", "\n} }"),
+        ],
+        #[cfg(feature = "c-sharp")]
+        TreeSitterLanguage::CSharp => &[("", ""), ("class C {\n", "\n}")],
+        #[cfg(feature = "php")]
+        TreeSitterLanguage::Php => &[("<?php\n", "")],
+        #[allow(unreachable_patterns)]
+        _ => &[("", "")],
+    }
+}
+pub fn code_confidence(code: &str, language: TreeSitterLanguage) -> Result<f64, Error> {
+    if code.trim().is_empty() {
+        return Ok(0.0);
+    }
+    let mut best: f64 = 0.0;
+    for (prefix, suffix) in wrappers(language) {
+        let source = format!("{}{}{}", prefix, code, suffix);
+        let tree = parse_source(&source, language)?;
+        let body = prefix.len()..prefix.len() + code.len();
+        best = best.max(score(tree.root_node(), &source, body));
+    }
+    Ok(best)
+}
+fn score(root: Node, source: &str, body: std::ops::Range<usize>) -> f64 {
+    let total = source[body.clone()]
+        .bytes()
+        .filter(|b| !b.is_ascii_whitespace())
+        .count();
+    let mut error_bytes = 0;
+    let mut missing = 0;
+    let mut leaves = 0;
+    let mut anonymous = 0;
+    let mut stack = vec![root];
+    while let Some(node) = stack.pop() {
+        if node.end_byte() < body.start || node.start_byte() > body.end {
+            continue;
+        }
+        if node.is_error() {
+            let start = node.start_byte().max(body.start);
+            let end = node.end_byte().min(body.end);
+            error_bytes += source[start..end]
+                .bytes()
+                .filter(|b| !b.is_ascii_whitespace())
+                .count();
+            continue;
+        }
+        if node.is_missing() {
+            missing += 1;
+            continue;
+        }
+        if node.child_count() == 0 {
+            if !node.kind().contains("comment") {
+                leaves += 1;
+                if !node.is_named() {
+                    anonymous += 1;
+                }
+            }
+            continue;
+        }
+        let mut cursor = node.walk();
+        stack.extend(node.children(&mut cursor));
+    }
+    if total == 0 || leaves == 0 {
+        return 0.0;
+    }
+    let clean = 1.0 - (error_bytes as f64 / total as f64).min(1.0);
+    let structure = (anonymous as f64 / leaves as f64 * 3.0).min(1.0);
+    clean * clean * structure * 0.75_f64.powi(missing)
+}
+#[cfg(all(test, any(feature = "python", feature = "rust-lang", feature = "c")))]
+mod tests {
+    use super::*;
+    #[test]
+    #[cfg(feature = "python")]
+    fn test_python_code_versus_prose() {
+        let lang = TreeSitterLanguage::Python;
+        assert!(code_confidence("print(total)", lang).unwrap() > 0.9);
+        assert!(code_confidence("for x in items:\n    y = x * 2", lang).unwrap() > 0.9);
+        assert!(code_confidence("Compute the total, then report it.", lang).unwrap() < 0.5);
+        assert!(code_confidence("cache", lang).unwrap() < 0.5);
+    }
+    #[test]
+    #[cfg(feature = "rust-lang")]
+    fn test_adjacent_line_comments_form_one_candidate() {
+        let input = "fn main() {\n    // let x = compute();\n    // println!(\"{}\", x);\n\n    // Explain why we skip this.\n    run();\n}\n";
+        let found = find_commented_code(input, TreeSitterLanguage::Rust).unwrap();
+        assert_eq!(found.len(), 2);
+        assert_eq!(found[0].comments.len(), 2);
+        assert_eq!(found[0].code, "let x = compute();\nprintln!(\"{}\", x);");
+        assert!(found[0].confidence >= 0.6);
+        assert!(found[1].confidence < 0.6);
+    }
+    #[test]
+    #[cfg(feature = "python")]
+    fn test_prose_lead_in_is_trimmed_from_block() {
+        let input = "# Old loader:\n# config = load(path)\n# if config is None:\n#     return\nx = 1  # y = 2\n";
+        let found = find_commented_code(input, TreeSitterLanguage::Python).unwrap();
+        assert_eq!(found.len(), 2);
+        assert_eq!(found[0].comments.len(), 3);
+        assert_eq!(found[0].comments[0].start.row, 1);
+        assert!(found[0].confidence >= 0.6);
+        assert_eq!(found[1].code, "y = 2");
+    }
+    #[test]
+    #[cfg(feature = "c")]
+    fn test_c_statement_needs_function_wrapper() {
+        let lang = TreeSitterLanguage::C;
+        assert!(code_confidence("if (x > 0) { return x; }", lang).unwrap() > 0.9);
+        assert!(code_confidence("This is only used by the parser", lang).unwrap() < 0.6);
+    }
+}
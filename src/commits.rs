@@ -0,0 +1,214 @@
+use crate::error::{EXIT_SUCCESS, Error};
+use crate::git::{self, BlobReader};
+use crate::hash::normalized_code;
+use crate::{Args, TreeSitterLanguage, find_comments, parse_language, resolve_language, walk};
+use std::io::{self, Write};
+const GITLINK_MODE: &str = "160000";
+#[derive(Debug, Clone, Copy, PartialEq, clap::ValueEnum)]
+pub enum CommitsFormat {
+    Table,
+    Json,
+}
+#[derive(clap::Args, Debug)]
+pub struct CommitsArgs {
+    #[arg(value_name = "RANGE")]
+    pub range: String,
+    #[arg(long, value_enum, value_name = "FORMAT", default_value = "table")]
+    pub format: CommitsFormat,
+}
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct Change {
+    pub comments: bool,
+    pub code: bool,
+}
+impl Change {
+    fn merge(&mut self, other: Change) {
+        self.comments |= other.comments;
+        self.code |= other.code;
+    }
+    pub fn class(&self) -> &'static str {
+        match (self.comments, self.code) {
+            (true, true) => "mixed",
+            (false, true) => "code",
+            (true, false) => "comment-only",
+            (false, false) => "empty",
+        }
+    }
+}
+struct FileChange {
+    path: String,
+    change: Change,
+}
+struct CommitReport {
+    commit: String,
+    subject: String,
+    change: Change,
+    files: Vec<FileChange>,
+}
+fn comment_texts(input: &str, language: TreeSitterLanguage) -> Result<Vec<String>, Error> {
+    Ok(find_comments(input, language)?
+        .iter()
+        .map(|c| c.text(input).trim().to_string())
+        .collect())
+}
+fn source_change(old: &str, new: &str, language: TreeSitterLanguage) -> Result<Change, Error> {
+    Ok(Change {
+        comments: comment_texts(old, language)? != comment_texts(new, language)?,
+        code: normalized_code(old, language)? != normalized_code(new, language)?,
+    })
+}
+pub fn classify_file(
+    path: &str,
+    old: &[u8],
+    new: &[u8],
+    language_override: Option<TreeSitterLanguage>,
+) -> Change {
+    if old == new {
+        return Change::default();
+    }
+    let code_change = Change {
+        comments: false,
+        code: true,
+    };
+    if !walk::is_wanted(path, language_override) {
+        return code_change;
+    }
+    let (Ok(old), Ok(new)) = (std::str::from_utf8(old), std::str::from_utf8(new)) else {
+        return code_change;
+    };
+    let change = resolve_language(path, language_override)
+        .and_then(|language| source_change(old, new, language))
+        .unwrap_or(code_change);
+    if change.code || change.comments {
+        change
+    } else {
+        Change {
+            comments: true,
+            code: false,
+        }
+    }
+}
+fn is_null(object: &str) -> bool {
+    !object.is_empty() && object.bytes().all(|b| b == b'0')
+}
+fn read_blob(blobs: &mut BlobReader, object: &str) -> Result<Vec<u8>, Error> {
+    if is_null(object) {
+        return Ok(Vec::new());
+    }
+    blobs.read(object)
+}
+fn classify_commit(
+    line: &str,
+    blobs: &mut BlobReader,
+    language: Option<TreeSitterLanguage>,
+) -> Result<CommitReport, Error> {
+    let mut ids = line.split(' ');
+    let commit = ids.next().unwrap_or_default().to_string();
+    let diff = match ids.next() {
+        Some(parent) => git::git(&["diff-tree", "-r", "-z", parent, &commit])?,
+        None => git::git(&["diff-tree", "-r", "-z", "--no-commit-id", "--root", &commit])?,
+    };
+    let mut fields = diff.split('\0').filter(|f| !f.is_empty());
+    let mut report = CommitReport {
+        subject: git::git(&["show", "-s", "--format=%s", &commit])?,
+        commit,
+        change: Change::default(),
+        files: Vec::new(),
+    };
+    while let (Some(meta), Some(path)) = (fields.next(), fields.next()) {
+        let meta: Vec<&str> = meta.trim_start_matches(':').split(' ').collect();
+        let [old_mode, new_mode, old_object, new_object, ..] = meta[..] else {
+            return Err(Error::Git(format!(
+                "unexpected git diff-tree output: {}",
+                diff
+            )));
+        };
+        let mut change = if old_mode == GITLINK_MODE || new_mode == GITLINK_MODE {
+            Change {
+                comments: false,
+                code: true,
+            }
+        } else {
+            let old = read_blob(blobs, old_object)?;
+            let new = read_blob(blobs, new_object)?;
+            classify_file(path, &old, &new, language)
+        };
+        change.code |= old_mode != new_mode && !is_null(old_object) && !is_null(new_object);
+        report.change.merge(change);
+        report.files.push(FileChange {
+            path: path.to_string(),
+            change,
+        });
+    }
+    Ok(report)
+}
+pub fn run(args: &CommitsArgs, cli: &Args) -> Result<i32, Error> {
+    let stdout = io::stdout();
+    let mut out = stdout.lock();
+    let language = cli.language.as_deref().map(parse_language).transpose()?;
+    let commits = git::git(&["rev-list", "--reverse", "--parents", &args.range, "--"])?;
+    let mut blobs = BlobReader::new()?;
+    let reports = commits
+        .lines()
+        .map(|line| classify_commit(line, &mut blobs, language))
+        .collect::<Result<Vec<_>, Error>>()?;
+    match args.format {
+        CommitsFormat::Table => {
+            for report in &reports {
+                writeln!(
+                    out,
+                    "{}  {:<12}  {}",
+                    &report.commit[..report.commit.len().min(12)],
+                    report.change.class(),
+                    report.subject
+                )?;
+            }
+        }
+        CommitsFormat::Json => {
+            let value: Vec<serde_json::Value> = reports
+                .iter()
+                .map(|report| {
+                    let files: Vec<serde_json::Value> = report
+                        .files
+                        .iter()
+                        .map(|file| {
+                            serde_json::json!({ "path": file.path, "class": file.change.class() })
+                        })
+                        .collect();
+                    serde_json::json!({
+                        "commit": report.commit,
+                        "subject": report.subject,
+                        "class": report.change.class(),
+                        "files": files,
+                    })
+                })
+                .collect();
+            let output = serde_json::to_string_pretty(&value).map_err(io::Error::other)?;
+            writeln!(out, "{}", output)?;
+        }
+    }
+    Ok(EXIT_SUCCESS)
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    #[cfg(feature = "python")]
+    fn test_classify_file() {
+        let old = b"x = 1  # one\n";
+        let class = |new: &[u8]| classify_file("a.py", old, new, None).class();
+        assert_eq!(class(b"x = 1  # uno\n"), "comment-only");
+        assert_eq!(class(b"x = 1\n\n"), "comment-only");
+        assert_eq!(class(b"x = 2  # one\n"), "code");
+        assert_eq!(class(b"x = 2  # two\n"), "mixed");
+        assert_eq!(classify_file("a.txt", b"a", b"b", None).class(), "code");
+        assert_eq!(classify_file("a.py", old, old, None).class(), "empty");
+    }
+    #[test]
+    fn test_null_objects_of_any_hash_length() {
+        assert!(is_null(&"0".repeat(40)));
+        assert!(is_null(&"0".repeat(64)));
+        assert!(!is_null("0000000000000000000000000000000000000001"));
+        assert!(!is_null(""));
+    }
+}
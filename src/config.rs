@@ -0,0 +1,80 @@
+use crate::classify::CommentKind;
+use crate::error::Error;
+use crate::filter::MatchOn;
+use serde::Deserialize;
+use std::fs;
+use std::path::Path;
+pub const DEFAULT_CONFIG: &str = ".rmcm.toml";
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub remove: Vec<CommentKind>,
+    pub keep: Vec<CommentKind>,
+    pub keep_todos: bool,
+    pub keep_matching: Vec<String>,
+    pub only_matching: Vec<String>,
+    pub match_on: Option<MatchOn>,
+    pub redact: Vec<RedactRuleConfig>,
+    pub redact_builtins: Option<bool>,
+}
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RedactRuleConfig {
+    #[serde(default = "default_rule_name")]
+    pub name: String,
+    pub pattern: String,
+    #[serde(default = "default_replacement")]
+    pub replacement: String,
+}
+fn default_rule_name() -> String {
+    "custom".to_string()
+}
+pub fn default_replacement() -> String {
+    "[REDACTED]".to_string()
+}
+impl Config {
+    pub fn load(path: Option<&str>, disabled: bool) -> Result<Config, Error> {
+        let path = match path {
+            Some(path) => path,
+            None if !disabled && Path::new(DEFAULT_CONFIG).is_file() => DEFAULT_CONFIG,
+            None => return Ok(Config::default()),
+        };
+        let text = fs::read_to_string(path).map_err(|e| Error::from(e).in_file(path))?;
+        Config::parse(&text).map_err(|e| e.in_file(path))
+    }
+    pub fn parse(text: &str) -> Result<Config, Error> {
+        toml::from_str(text).map_err(|e| Error::Config(e.message().to_string()))
+    }
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn test_parse_filter_rules() {
+        let config = Config::parse(
+            "keep = [\"license\", \"separator\"]\nkeep_matching = [\"@copyright\"]\nonly_matching = [\"(?i)generated by\"]\nmatch_on = \"body\"\n",
+        )
+        .unwrap();
+        assert_eq!(config.keep, [CommentKind::License, CommentKind::Banner]);
+        assert_eq!(config.keep_matching, ["@copyright"]);
+        assert_eq!(config.only_matching, ["(?i)generated by"]);
+        assert_eq!(config.match_on, Some(MatchOn::Body));
+    }
+    #[test]
+    fn test_parse_redact_rules() {
+        let config = Config::parse(
+            "redact_builtins = false\n[[redact]]\nname = \"customer\"\npattern = \"ACME-\\\\d+\"\n[[redact]]\npattern = \"x\"\n",
+        )
+        .unwrap();
+        assert_eq!(config.redact_builtins, Some(false));
+        assert_eq!(config.redact[0].name, "customer");
+        assert_eq!(config.redact[0].pattern, "ACME-\\d+");
+        assert_eq!(config.redact[1].name, "custom");
+        assert_eq!(config.redact[1].replacement, "[REDACTED]");
+    }
+    #[test]
+    fn test_unknown_keys_are_rejected() {
+        let err = Config::parse("keep_matchng = [\"x\"]").unwrap_err();
+        assert_eq!(err.exit_code(), crate::error::EXIT_CONFIG);
+    }
+}
@@ -0,0 +1,139 @@
+use crate::error::{EXIT_DIFFERS, EXIT_SUCCESS, Error};
+use crate::hash::normalized_code;
+use crate::{Args, TreeSitterLanguage, parse_language, read_source, resolve_language, walk};
+use similar::TextDiff;
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::{self, Write};
+use std::path::Path;
+#[derive(clap::Args, Debug)]
+pub struct DiffArgs {
+    #[arg(value_name = "OLD")]
+    pub old: String,
+    #[arg(value_name = "NEW")]
+    pub new: String,
+    #[arg(short = 'U', long, value_name = "N", default_value_t = 3)]
+    pub context: usize,
+    #[arg(short, long)]
+    pub quiet: bool,
+}
+fn relative_files(
+    root: &str,
+    language: Option<TreeSitterLanguage>,
+) -> Result<BTreeMap<String, String>, Error> {
+    Ok(walk::collect_files(&[root.to_string()], language)?
+        .into_iter()
+        .map(|file| {
+            let relative = Path::new(&file)
+                .strip_prefix(root)
+                .map(|p| p.to_string_lossy().into_owned())
+                .unwrap_or_else(|_| file.clone());
+            (relative, file)
+        })
+        .collect())
+}
+fn code(path: Option<&str>, language: Option<TreeSitterLanguage>) -> Result<String, Error> {
+    let Some(path) = path else {
+        return Ok(String::new());
+    };
+    let result = resolve_language(path, language).and_then(|language| {
+        let input = read_source(fs::File::open(path)?)?;
+        normalized_code(&input, language)
+    });
+    result.map_err(|e| e.in_file(path))
+}
+pub fn unified_diff(
+    old: &str,
+    new: &str,
+    old_name: &str,
+    new_name: &str,
+    context: usize,
+) -> String {
+    TextDiff::from_lines(old, new)
+        .unified_diff()
+        .context_radius(context)
+        .header(old_name, new_name)
+        .to_string()
+}
+pub fn run(args: &DiffArgs, cli: &Args) -> Result<i32, Error> {
+    let stdout = io::stdout();
+    let mut out = stdout.lock();
+    let language = cli.language.as_deref().map(parse_language).transpose()?;
+    let is_dir = |path: &str| {
+        fs::metadata(path)
+            .map(|m| m.is_dir())
+            .map_err(|e| Error::from(e).in_file(path))
+    };
+    let pairs: Vec<(Option<String>, Option<String>)> =
+        match (is_dir(&args.old)?, is_dir(&args.new)?) {
+            (false, false) => vec![(Some(args.old.clone()), Some(args.new.clone()))],
+            (true, true) => {
+                let mut old = relative_files(&args.old, language)?;
+                let mut new = relative_files(&args.new, language)?;
+                let mut names: Vec<String> = old.keys().chain(new.keys()).cloned().collect();
+                names.sort();
+                names.dedup();
+                names
+                    .into_iter()
+                    .map(|name| (old.remove(&name), new.remove(&name)))
+                    .collect()
+            }
+            _ => {
+                return Err(Error::Usage(
+                    "diff needs two files or two directories".to_string(),
+                ));
+            }
+        };
+    let mut differs = false;
+    let mut first_failure = None;
+    for (old, new) in pairs {
+        let codes = code(old.as_deref(), language)
+            .and_then(|old_code| Ok((old_code, code(new.as_deref(), language)?)));
+        let (old_code, new_code) = match codes {
+            Ok(codes) => codes,
+            Err(e) => {
+                if !cli.force {
+                    return Err(e);
+                }
+                e.report(cli.error_format);
+                first_failure.get_or_insert(e.exit_code());
+                continue;
+            }
+        };
+        if old_code == new_code {
+            continue;
+        }
+        differs = true;
+        if args.quiet {
+            continue;
+        }
+        let old_name = old.unwrap_or_else(|| "/dev/null".to_string());
+        let new_name = new.unwrap_or_else(|| "/dev/null".to_string());
+        write!(
+            out,
+            "{}",
+            unified_diff(&old_code, &new_code, &old_name, &new_name, args.context)
+        )?;
+    }
+    Ok(match first_failure {
+        Some(code) => code,
+        None if differs => EXIT_DIFFERS,
+        None => EXIT_SUCCESS,
+    })
+}
+#[cfg(all(test, feature = "python"))]
+mod tests {
+    use super::*;
+    #[test]
+    fn test_comment_only_changes_have_no_diff() {
+        let lang = TreeSitterLanguage::Python;
+        let old = normalized_code("x = 1  # one\n\ny = 2\n", lang).unwrap();
+        let new = normalized_code("# values\nx = 1\ny = 2  # two\n", lang).unwrap();
+        assert_eq!(old, new);
+        let changed = normalized_code("x = 1\ny = 3\n", lang).unwrap();
+        assert_eq!(
+            unified_diff(&old, &changed, "a.py", "b.py", 3),
+            "--- a.py\n+++ b.py\n@@ -1,2 +1,2 @@\n x = 1\n-y = 2\n+y = 3\n"
+        );
+    }
+}
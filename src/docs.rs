@@ -0,0 +1,542 @@
+use crate::error::Error;
+use crate::{Args, TreeSitterLanguage, find_comments_in_tree, for_each_source, parse_source};
+use std::collections::HashSet;
+use std::io::{self, Write};
+use std::path::Path;
+use tree_sitter::Node;
+#[derive(clap::Args, Debug)]
+pub struct DocsArgs {
+    #[arg(value_name = "PATHS")]
+    pub paths: Vec<String>,
+}
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum DocStyle {
+    Rust,
+    JavaDoc,
+    CSharp,
+    Go,
+    Python,
+}
+#[derive(Debug, Clone, PartialEq)]
+pub struct DocEntry {
+    pub name: String,
+    pub kind: &'static str,
+    pub path: String,
+    pub signature: String,
+    pub line: usize,
+    pub doc: String,
+}
+struct Context<'a> {
+    input: &'a str,
+    style: DocStyle,
+    comment_starts: HashSet<usize>,
+    prefix: Option<String>,
+}
+pub fn run(args: &DocsArgs, cli: &Args) -> Result<i32, Error> {
+    let stdout = io::stdout();
+    let mut out = stdout.lock();
+    let mut records = Vec::new();
+    let code = for_each_source(&args.paths, cli, |path, language, input| {
+        for entry in extract_docs(input, language, path)? {
+            records.push(serde_json::json!({
+                "file": path,
+                "language": language.name(),
+                "name": entry.name,
+                "kind": entry.kind,
+                "path": entry.path,
+                "signature": entry.signature,
+                "line": entry.line,
+                "doc": entry.doc,
+            }));
+        }
+        Ok(())
+    })?;
+    let output = serde_json::to_string_pretty(&records).map_err(io::Error::other)?;
+    writeln!(out, "{}", output)?;
+    Ok(code)
+}
+fn doc_style(language: TreeSitterLanguage) -> Option<DocStyle> {
+    match language.name() {
+        "rust" => Some(DocStyle::Rust),
+        "java" | "javascript" | "typescript" => Some(DocStyle::JavaDoc),
+        "c#" => Some(DocStyle::CSharp),
+        "go" => Some(DocStyle::Go),
+        "python" => Some(DocStyle::Python),
+        _ => None,
+    }
+}
+pub fn extract_docs(
+    input: &str,
+    language: TreeSitterLanguage,
+    file_path: &str,
+) -> Result<Vec<DocEntry>, Error> {
+    let Some(style) = doc_style(language) else {
+        return Ok(Vec::new());
+    };
+    let tree = parse_source(input, language)?;
+    let comments = find_comments_in_tree(&tree, input, language)?;
+    let root = tree.root_node();
+    let ctx = Context {
+        input,
+        style,
+        comment_starts: comments.iter().map(|c| c.range.start).collect(),
+        prefix: module_prefix(root, input, style),
+    };
+    let mut entries = Vec::new();
+    if style == DocStyle::Python
+        && let Some(doc) = python_docstring(root, input)
+    {
+        let name = Path::new(file_path)
+            .file_stem()
+            .map(|s| s.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        entries.push(DocEntry {
+            path: name.clone(),
+            name,
+            kind: "module",
+            signature: String::new(),
+            line: 1,
+            doc,
+        });
+    }
+    collect_docs(root, &ctx, &mut entries);
+    Ok(entries)
+}
+fn collect_docs(node: Node, ctx: &Context, entries: &mut Vec<DocEntry>) {
+    let mut cursor = node.walk();
+    let children: Vec<Node> = node.named_children(&mut cursor).collect();
+    let mut pending: Vec<Node> = Vec::new();
+    for child in children {
+        if ctx.comment_starts.contains(&child.start_byte()) {
+            let text = text_of(child, ctx.input);
+            if is_doc_comment(ctx.style, text) {
+                if ctx.style == DocStyle::Go
+                    && pending
+                        .last()
+                        .is_some_and(|prev| !adjacent(*prev, child, ctx.input))
+                {
+                    pending.clear();
+                }
+                pending.push(child);
+            } else {
+                pending.clear();
+            }
+            continue;
+        }
+        if child.kind() == "attribute_item" {
+            continue;
+        }
+        let decl = unwrap_declaration(child);
+        if ctx.style == DocStyle::Python {
+            if matches!(decl.kind(), "function_definition" | "class_definition")
+                && let Some(doc) = decl
+                    .child_by_field_name("body")
+                    .and_then(|body| python_docstring(body, ctx.input))
+            {
+                entries.extend(make_entry(decl, ctx, doc));
+            }
+        } else if let Some(last) = pending.last() {
+            let attached = ctx.style != DocStyle::Go || adjacent(*last, child, ctx.input);
+            if attached {
+                let doc = pending
+                    .iter()
+                    .map(|c| clean_comment(text_of(*c, ctx.input)))
+                    .collect::<Vec<_>>()
+                    .join("\n");
+                entries.extend(make_entry(decl, ctx, doc));
+            }
+        }
+        pending.clear();
+        collect_docs(child, ctx, entries);
+    }
+}
+fn make_entry(decl: Node, ctx: &Context, doc: String) -> Option<DocEntry> {
+    let mut kind = symbol_kind(ctx.style, decl)?;
+    let name = symbol_name(decl, ctx.input)?;
+    let mut segments = Vec::new();
+    let mut in_type = false;
+    let mut ancestor = decl.parent();
+    while let Some(node) = ancestor {
+        if let Some(container) = container_name(ctx.style, node, ctx.input) {
+            if segments.is_empty() {
+                in_type = !matches!(symbol_kind(ctx.style, node), Some("module" | "namespace"));
+            }
+            segments.push(container);
+        }
+        ancestor = node.parent();
+    }
+    if ctx.style == DocStyle::Go
+        && decl.kind() == "method_declaration"
+        && let Some(receiver) = go_receiver(decl, ctx.input)
+    {
+        segments.push(receiver);
+    }
+    if let Some(prefix) = &ctx.prefix {
+        segments.push(prefix.clone());
+    }
+    segments.reverse();
+    segments.push(name.clone());
+    if kind == "function" && in_type {
+        kind = "method";
+    }
+    let separator = if ctx.style == DocStyle::Rust {
+        "::"
+    } else {
+        "."
+    };
+    Some(DocEntry {
+        name,
+        kind,
+        path: segments.join(separator),
+        signature: signature(decl, ctx.input),
+        line: decl.start_position().row + 1,
+        doc,
+    })
+}
+fn is_doc_comment(style: DocStyle, text: &str) -> bool {
+    match style {
+        DocStyle::Rust => {
+            (text.starts_with("///") && !text.starts_with("////"))
+                || (text.starts_with("/**") && !text.starts_with("/**/"))
+        }
+        DocStyle::JavaDoc => text.starts_with("/**") && !text.starts_with("/**/"),
+        DocStyle::CSharp => text.starts_with("///") || text.starts_with("/**"),
+        DocStyle::Go => true,
+        DocStyle::Python => false,
+    }
+}
+fn adjacent(first: Node, second: Node, input: &str) -> bool {
+    input[first.end_byte()..second.start_byte()]
+        .matches('\n')
+        .count()
+        <= 1
+}
+fn unwrap_declaration(node: Node) -> Node {
+    let inner = match node.kind() {
+        "export_statement" => node.child_by_field_name("declaration"),
+        "decorated_definition" => node.child_by_field_name("definition"),
+        "expression_statement" => node
+            .named_child(0)
+            .filter(|n| matches!(n.kind(), "internal_module" | "module")),
+        _ => None,
+    };
+    inner.unwrap_or(node)
+}
+fn symbol_kind(style: DocStyle, node: Node) -> Option<&'static str> {
+    let kind = match (style, node.kind()) {
+        (DocStyle::Rust, "function_item" | "function_signature_item") => "function",
+        (DocStyle::Rust, "struct_item") => "struct",
+        (DocStyle::Rust, "enum_item") => "enum",
+        (DocStyle::Rust, "union_item") => "union",
+        (DocStyle::Rust, "enum_variant") => "variant",
+        (DocStyle::Rust, "trait_item") => "trait",
+        (DocStyle::Rust, "field_declaration") => "field",
+        (DocStyle::Rust, "mod_item") => "module",
+        (DocStyle::Rust, "const_item") => "const",
+        (DocStyle::Rust, "static_item") => "static",
+        (DocStyle::Rust, "type_item" | "associated_type") => "type",
+        (DocStyle::Rust, "macro_definition") => "macro",
+        (DocStyle::JavaDoc, "class_declaration" | "abstract_class_declaration") => "class",
+        (DocStyle::JavaDoc, "interface_declaration") => "interface",
+        (DocStyle::JavaDoc, "enum_declaration") => "enum",
+        (DocStyle::JavaDoc, "record_declaration") => "record",
+        (DocStyle::JavaDoc, "annotation_type_declaration") => "annotation",
+        (DocStyle::JavaDoc, "enum_constant") => "variant",
+        (
+            DocStyle::JavaDoc,
+            "method_declaration"
+            | "method_definition"
+            | "method_signature"
+            | "abstract_method_signature",
+        ) => "method",
+        (DocStyle::JavaDoc, "constructor_declaration") => "constructor",
+        (DocStyle::JavaDoc, "function_declaration" | "generator_function_declaration") => {
+            "function"
+        }
+        (
+            DocStyle::JavaDoc,
+            "field_declaration"
+            | "constant_declaration"
+            | "public_field_definition"
+            | "field_definition"
+            | "property_signature",
+        ) => "field",
+        (DocStyle::JavaDoc, "type_alias_declaration") => "type",
+        (DocStyle::JavaDoc, "lexical_declaration" | "variable_declaration") => "variable",
+        (DocStyle::JavaDoc, "internal_module" | "module") => "namespace",
+        (DocStyle::CSharp, "class_declaration") => "class",
+        (DocStyle::CSharp, "struct_declaration") => "struct",
+        (DocStyle::CSharp, "interface_declaration") => "interface",
+        (DocStyle::CSharp, "enum_declaration") => "enum",
+        (DocStyle::CSharp, "record_declaration") => "record",
+        (DocStyle::CSharp, "method_declaration") => "method",
+        (DocStyle::CSharp, "constructor_declaration") => "constructor",
+        (DocStyle::CSharp, "property_declaration") => "property",
+        (DocStyle::CSharp, "field_declaration") => "field",
+        (DocStyle::CSharp, "event_declaration" | "event_field_declaration") => "event",
+        (DocStyle::CSharp, "enum_member_declaration") => "variant",
+        (DocStyle::CSharp, "namespace_declaration") => "namespace",
+        (DocStyle::CSharp, "delegate_declaration") => "delegate",
+        (DocStyle::Go, "function_declaration") => "function",
+        (DocStyle::Go, "method_declaration" | "method_elem" | "method_spec") => "method",
+        (DocStyle::Go, "field_declaration") => "field",
+        (DocStyle::Go, "const_declaration") => "const",
+        (DocStyle::Go, "var_declaration") => "var",
+        (DocStyle::Go, "type_declaration") => {
+            let spec_type = node
+                .named_child(0)
+                .and_then(|spec| spec.child_by_field_name("type"));
+            match spec_type.map(|t| t.kind()) {
+                Some("struct_type") => "struct",
+                Some("interface_type") => "interface",
+                _ => "type",
+            }
+        }
+        (DocStyle::Python, "function_definition") => "function",
+        (DocStyle::Python, "class_definition") => "class",
+        _ => return None,
+    };
+    Some(kind)
+}
+fn symbol_name(node: Node, input: &str) -> Option<String> {
+    if let Some(name) = node.child_by_field_name("name") {
+        return Some(text_of(name, input).to_string());
+    }
+    let mut cursor = node.walk();
+    let children: Vec<Node> = node.named_children(&mut cursor).collect();
+    children
+        .into_iter()
+        .filter(|child| {
+            matches!(
+                child.kind(),
+                "variable_declarator"
+                    | "variable_declaration"
+                    | "type_spec"
+                    | "const_spec"
+                    | "var_spec"
+            )
+        })
+        .find_map(|child| symbol_name(child, input))
+}
+fn container_name(style: DocStyle, node: Node, input: &str) -> Option<String> {
+    if style == DocStyle::Rust && node.kind() == "impl_item" {
+        let ty = node.child_by_field_name("type")?;
+        let text = text_of(ty, input);
+        return Some(text.split('<').next().unwrap_or(text).trim().to_string());
+    }
+    let kind = symbol_kind(style, node)?;
+    if matches!(kind, "field" | "variable" | "const" | "var" | "static") {
+        return None;
+    }
+    symbol_name(node, input)
+}
+fn module_prefix(root: Node, input: &str, style: DocStyle) -> Option<String> {
+    let mut cursor = root.walk();
+    let children: Vec<Node> = root.named_children(&mut cursor).collect();
+    children
+        .into_iter()
+        .find_map(|child| match (style, child.kind()) {
+            (DocStyle::Go, "package_clause") | (DocStyle::JavaDoc, "package_declaration") => {
+                child.named_child(0).map(|n| text_of(n, input).to_string())
+            }
+            (DocStyle::CSharp, "file_scoped_namespace_declaration") => child
+                .child_by_field_name("name")
+                .map(|n| text_of(n, input).to_string()),
+            _ => None,
+        })
+}
+fn go_receiver(decl: Node, input: &str) -> Option<String> {
+    let receiver = decl.child_by_field_name("receiver")?;
+    let param = receiver.named_child(0)?;
+    let ty = text_of(param.child_by_field_name("type")?, input);
+    let ty = ty.trim_start_matches('*');
+    Some(ty.split('[').next().unwrap_or(ty).to_string())
+}
+fn python_docstring(body: Node, input: &str) -> Option<String> {
+    let first = body.named_child(0)?;
+    if first.kind() != "expression_statement" || first.named_child_count() != 1 {
+        return None;
+    }
+    let string = first.named_child(0)?;
+    if string.kind() != "string" {
+        return None;
+    }
+    Some(clean_docstring(text_of(string, input)))
+}
+fn signature(node: Node, input: &str) -> String {
+    let text = match node.child_by_field_name("body") {
+        Some(body) => &input[node.start_byte()..body.start_byte()],
+        None => text_of(node, input).lines().next().unwrap_or(""),
+    };
+    let collapsed = text.split_whitespace().collect::<Vec<_>>().join(" ");
+    collapsed
+        .trim_end_matches(['{', ';'])
+        .trim_end()
+        .to_string()
+}
+fn text_of<'a>(node: Node, input: &'a str) -> &'a str {
+    &input[node.byte_range()]
+}
+fn clean_comment(text: &str) -> String {
+    let text = text.trim_end();
+    let lines: Vec<String> = if text.starts_with("/*") && text.ends_with("*/") && text.len() >= 4 {
+        let body = &text[2..text.len() - 2];
+        let body = body.strip_prefix(['*', '!']).unwrap_or(body);
+        body.lines()
+            .map(|line| {
+                let line = line.trim_start();
+                let line = line.strip_prefix('*').unwrap_or(line);
+                line.strip_prefix(' ')
+                    .unwrap_or(line)
+                    .trim_end()
+                    .to_string()
+            })
+            .collect()
+    } else {
+        text.lines()
+            .map(|line| {
+                let line = line.trim_start();
+                let line = ["///", "//!", "//"]
+                    .iter()
+                    .find_map(|marker| line.strip_prefix(marker))
+                    .unwrap_or(line);
+                line.strip_prefix(' ')
+                    .unwrap_or(line)
+                    .trim_end()
+                    .to_string()
+            })
+            .collect()
+    };
+    trim_blank_lines(lines)
+}
+fn clean_docstring(text: &str) -> String {
+    let text = text.trim_start_matches(['r', 'R', 'u', 'U', 'b', 'B', 'f', 'F']);
+    let body = ["\"\"\"", "'''", "\"", "'"]
+        .iter()
+        .find(|q| text.len() >= 2 * q.len() && text.starts_with(*q) && text.ends_with(*q))
+        .map(|q| &text[q.len()..text.len() - q.len()])
+        .unwrap_or(text);
+    let mut lines = body.lines();
+    let first = lines.next().unwrap_or("").trim().to_string();
+    let rest: Vec<&str> = lines.collect();
+    let indent = rest
+        .iter()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| line.len() - line.trim_start().len())
+        .min()
+        .unwrap_or(0);
+    let mut cleaned = vec![first];
+    cleaned.extend(
+        rest.iter()
+            .map(|line| line.get(indent..).unwrap_or("").trim_end().to_string()),
+    );
+    trim_blank_lines(cleaned)
+}
+fn trim_blank_lines(lines: Vec<String>) -> String {
+    let start = lines
+        .iter()
+        .position(|l| !l.is_empty())
+        .unwrap_or(lines.len());
+    let end = lines
+        .iter()
+        .rposition(|l| !l.is_empty())
+        .map_or(start, |i| i + 1);
+    lines[start..end].join("\n")
+}
+#[cfg(all(
+    test,
+    any(
+        feature = "rust-lang",
+        feature = "python",
+        feature = "go",
+        feature = "java",
+        feature = "typescript",
+        feature = "c-sharp"
+    )
+))]
+mod tests {
+    use super::*;
+    fn find<'a>(entries: &'a [DocEntry], path: &str) -> &'a DocEntry {
+        entries
+            .iter()
+            .find(|e| e.path == path)
+            .unwrap_or_else(|| panic!("no entry for {}: {:?}", path, entries))
+    }
+    #[test]
+    #[cfg(feature = "rust-lang")]
+    fn test_rust_docs_with_impl_path() {
+        let input = "/// A point.\n#[derive(Debug)]\npub struct Point {\n    /// X coordinate.\n    pub x: i32,\n}\n// plain\nfn skipped() {}\nimpl Point {\n    /** Builds one.\n     * Really.\n     */\n    pub fn new(x: i32) -> Self\n    where\n        Self: Sized,\n    { Point { x } }\n}\n";
+        let entries = extract_docs(input, TreeSitterLanguage::Rust, "p.rs").unwrap();
+        assert_eq!(entries.len(), 3);
+        let point = find(&entries, "Point");
+        assert_eq!(point.kind, "struct");
+        assert_eq!(point.signature, "pub struct Point");
+        assert_eq!(point.doc, "A point.");
+        assert_eq!(find(&entries, "Point::x").kind, "field");
+        let new = find(&entries, "Point::new");
+        assert_eq!(new.kind, "method");
+        assert_eq!(new.doc, "Builds one.\nReally.");
+        assert_eq!(
+            new.signature,
+            "pub fn new(x: i32) -> Self where Self: Sized,"
+        );
+        assert_eq!(new.line, 13);
+    }
+    #[test]
+    #[cfg(feature = "python")]
+    fn test_python_docstrings() {
+        let input = "\"\"\"Module doc.\"\"\"\nclass Greeter:\n    \"\"\"Says hi.\n\n    Details.\n        Indented.\n    \"\"\"\n    def greet(self):\n        r'''Greets.'''\n        pass\ndef bare():\n    pass\n";
+        let entries = extract_docs(input, TreeSitterLanguage::Python, "pkg/hello.py").unwrap();
+        assert_eq!(entries.len(), 3);
+        assert_eq!(find(&entries, "hello").kind, "module");
+        assert_eq!(
+            find(&entries, "Greeter").doc,
+            "Says hi.\n\nDetails.\n    Indented."
+        );
+        let greet = find(&entries, "Greeter.greet");
+        assert_eq!(greet.kind, "method");
+        assert_eq!(greet.doc, "Greets.");
+        assert_eq!(greet.signature, "def greet(self):");
+    }
+    #[test]
+    #[cfg(feature = "go")]
+    fn test_go_doc_requires_adjacency() {
+        let input = "package shapes\n// Area computes.\nfunc (s *Square) Area() int { return 0 }\n// Detached.\n\nfunc Lost() {}\n// Square is.\ntype Square struct {\n\t// Side length.\n\tSide int\n}\n";
+        let entries = extract_docs(input, TreeSitterLanguage::Go, "s.go").unwrap();
+        assert_eq!(entries.len(), 3);
+        assert_eq!(find(&entries, "shapes.Square.Area").kind, "method");
+        assert_eq!(find(&entries, "shapes.Square").kind, "struct");
+        assert_eq!(find(&entries, "shapes.Square.Side").doc, "Side length.");
+    }
+    #[test]
+    #[cfg(feature = "java")]
+    fn test_java_javadoc() {
+        let input = "package com.acme;\n/** A widget. */\npublic class Widget {\n  /** Size. */\n  int size;\n  /* not doc */\n  void hidden() {}\n  /** Resizes.\n   * @param n size\n   */\n  public void resize(int n) {}\n}\n";
+        let entries = extract_docs(input, TreeSitterLanguage::Java, "W.java").unwrap();
+        assert_eq!(entries.len(), 3);
+        assert_eq!(find(&entries, "com.acme.Widget.size").kind, "field");
+        let resize = find(&entries, "com.acme.Widget.resize");
+        assert_eq!(resize.doc, "Resizes.\n@param n size");
+        assert_eq!(resize.signature, "public void resize(int n)");
+    }
+    #[test]
+    #[cfg(feature = "typescript")]
+    fn test_typescript_exported_declarations() {
+        let input = "/** Adds. */\nexport function add(a: number): number { return a; }\n/** Shape. */\nexport class Shape {\n  /** Area. */\n  area(): number { return 0; }\n}\n/** Options. */\ninterface Options { /** Verbose. */ verbose: boolean; }\n";
+        let entries = extract_docs(input, TreeSitterLanguage::TypeScript, "a.ts").unwrap();
+        assert_eq!(find(&entries, "add").kind, "function");
+        assert_eq!(find(&entries, "Shape.area").kind, "method");
+        assert_eq!(find(&entries, "Options.verbose").kind, "field");
+    }
+    #[test]
+    #[cfg(feature = "c-sharp")]
+    fn test_csharp_xml_docs() {
+        let input = "namespace Acme.Tools {\n  /// <summary>Hammer.</summary>\n  public class Hammer {\n    /// <summary>Hits.</summary>\n    public void Hit(int force) {}\n    /// Weight.\n    public int Weight { get; set; }\n  }\n}\n";
+        let entries = extract_docs(input, TreeSitterLanguage::CSharp, "H.cs").unwrap();
+        assert_eq!(entries.len(), 3);
+        assert_eq!(find(&entries, "Acme.Tools.Hammer").kind, "class");
+        let hit = find(&entries, "Acme.Tools.Hammer.Hit");
+        assert_eq!(hit.doc, "<summary>Hits.</summary>");
+        assert_eq!(find(&entries, "Acme.Tools.Hammer.Weight").kind, "property");
+    }
+}
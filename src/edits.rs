@@ -0,0 +1,72 @@
+use crate::error::Error;
+use crate::sourcemap::LineIndex;
+use crate::{Comment, TreeSitterLanguage};
+use serde_json::{Value, json};
+use std::io::{self, Write};
+#[derive(Debug, Clone, Copy, PartialEq, clap::ValueEnum)]
+pub enum OutputFormat {
+    Text,
+    EditsJson,
+}
+fn position(index: &LineIndex, point: tree_sitter::Point, offset: usize) -> Value {
+    json!({
+        "line": point.row,
+        "utf8": point.column,
+        "utf16": index.position(offset).1,
+    })
+}
+fn replacement(text: &str) -> String {
+    "\n".repeat(text.matches('\n').count())
+}
+pub fn file_edits(
+    path: &str,
+    language: TreeSitterLanguage,
+    input: &str,
+    comments: &[Comment],
+) -> Value {
+    let index = LineIndex::new(input);
+    let edits: Vec<Value> = comments
+        .iter()
+        .map(|comment| {
+            json!({
+                "start_byte": comment.range.start,
+                "end_byte": comment.range.end,
+                "start": position(&index, comment.start, comment.range.start),
+                "end": position(&index, comment.end, comment.range.end),
+                "replacement": replacement(comment.text(input)),
+            })
+        })
+        .collect();
+    json!({ "file": path, "language": language.name(), "edits": edits })
+}
+pub fn print(files: &[Value]) -> Result<(), Error> {
+    let output = serde_json::to_string_pretty(files).map_err(io::Error::other)?;
+    writeln!(io::stdout().lock(), "{}", output)?;
+    Ok(())
+}
+#[cfg(all(test, feature = "javascript"))]
+mod tests {
+    use super::*;
+    use crate::{find_comments, remove_comments_treesitter};
+    #[test]
+    fn test_edits_match_splice() {
+        let input = "let s = \"\u{1f600}\"; // smile\n/* a\r\n b */ f();\n";
+        let lang = TreeSitterLanguage::JavaScript;
+        let comments = find_comments(input, lang).unwrap();
+        let value = file_edits("a.js", lang, input, &comments);
+        let edits = value["edits"].as_array().unwrap();
+        assert_eq!(
+            edits[0]["start"],
+            json!({"line": 0, "utf8": 16, "utf16": 14})
+        );
+        assert_eq!(edits[1]["end"], json!({"line": 2, "utf8": 5, "utf16": 5}));
+        assert_eq!(edits[1]["replacement"], "\n");
+        let mut output = input.to_string();
+        for edit in edits.iter().rev() {
+            let range = edit["start_byte"].as_u64().unwrap() as usize
+                ..edit["end_byte"].as_u64().unwrap() as usize;
+            output.replace_range(range, edit["replacement"].as_str().unwrap());
+        }
+        assert_eq!(output, remove_comments_treesitter(input, lang).unwrap());
+    }
+}
@@ -0,0 +1,194 @@
+use std::fmt;
+use std::io;
+pub const EXIT_SUCCESS: i32 = 0;
+pub const EXIT_COMMENTS_FOUND: i32 = 1;
+pub const EXIT_DIFFERS: i32 = EXIT_COMMENTS_FOUND;
+pub const EXIT_USAGE: i32 = 2;
+pub const EXIT_UNSUPPORTED_LANGUAGE: i32 = 3;
+pub const EXIT_IO: i32 = 4;
+pub const EXIT_INVALID_UTF8: i32 = 5;
+pub const EXIT_GRAMMAR_LOAD: i32 = 6;
+pub const EXIT_QUERY_COMPILE: i32 = 7;
+pub const EXIT_PARSE: i32 = 8;
+pub const EXIT_VERIFY: i32 = 9;
+pub const EXIT_CONFIG: i32 = 10;
+pub const EXIT_UNPLACED_COMMENTS: i32 = 11;
+pub const EXIT_GIT: i32 = 12;
+pub const EXIT_SECRETS_FOUND: i32 = 13;
+#[derive(Debug)]
+pub enum Error {
+    Usage(String),
+    UnsupportedLanguage(String),
+    Io(io::Error),
+    InvalidUtf8,
+    GrammarLoad(tree_sitter::LanguageError),
+    QueryCompile(tree_sitter::QueryError),
+    Parse,
+    Verify(String),
+    Config(String),
+    Git(String),
+    File { path: String, source: Box<Error> },
+}
+#[derive(Debug, Clone, Copy, PartialEq, clap::ValueEnum)]
+pub enum ErrorFormat {
+    Text,
+    Json,
+}
+impl Error {
+    pub fn in_file(self, path: &str) -> Error {
+        match self {
+            Error::File { .. } => self,
+            other => Error::File {
+                path: path.to_string(),
+                source: Box::new(other),
+            },
+        }
+    }
+    pub fn root(&self) -> &Error {
+        match self {
+            Error::File { source, .. } => source.root(),
+            other => other,
+        }
+    }
+    pub fn path(&self) -> Option<&str> {
+        match self {
+            Error::File { path, .. } => Some(path),
+            _ => None,
+        }
+    }
+    pub fn is_broken_pipe(&self) -> bool {
+        matches!(self.root(), Error::Io(e) if e.kind() == io::ErrorKind::BrokenPipe)
+    }
+    pub fn kind(&self) -> &'static str {
+        match self.root() {
+            Error::Usage(_) => "usage",
+            Error::UnsupportedLanguage(_) => "unsupported_language",
+            Error::Io(_) => "io",
+            Error::InvalidUtf8 => "invalid_utf8",
+            Error::GrammarLoad(_) => "grammar_load",
+            Error::QueryCompile(_) => "query_compile",
+            Error::Parse => "parse",
+            Error::Verify(_) => "verify",
+            Error::Config(_) => "config",
+            Error::Git(_) => "git",
+            Error::File { .. } => unreachable!(),
+        }
+    }
+    pub fn exit_code(&self) -> i32 {
+        match self.root() {
+            Error::Usage(_) => EXIT_USAGE,
+            Error::UnsupportedLanguage(_) => EXIT_UNSUPPORTED_LANGUAGE,
+            Error::Io(_) => EXIT_IO,
+            Error::InvalidUtf8 => EXIT_INVALID_UTF8,
+            Error::GrammarLoad(_) => EXIT_GRAMMAR_LOAD,
+            Error::QueryCompile(_) => EXIT_QUERY_COMPILE,
+            Error::Parse => EXIT_PARSE,
+            Error::Verify(_) => EXIT_VERIFY,
+            Error::Config(_) => EXIT_CONFIG,
+            Error::Git(_) => EXIT_GIT,
+            Error::File { .. } => unreachable!(),
+        }
+    }
+    pub fn report(&self, format: ErrorFormat) {
+        match format {
+            ErrorFormat::Text => {
+                eprintln!("Error: {}", self);
+                if let Error::UnsupportedLanguage(_) = self.root() {
+                    eprintln!(
+                        "Supported languages in this build: {}",
+                        crate::get_supported_languages()
+                    );
+                }
+            }
+            ErrorFormat::Json => {
+                let value = serde_json::json!({
+                    "error": self.kind(),
+                    "exit_code": self.exit_code(),
+                    "path": self.path(),
+                    "message": self.root().to_string(),
+                });
+                eprintln!("{}", value);
+            }
+        }
+    }
+}
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Usage(msg) => write!(f, "{}", msg),
+            Error::UnsupportedLanguage(msg) => write!(f, "{}", msg),
+            Error::Io(e) => write!(f, "{}", e),
+            Error::InvalidUtf8 => write!(f, "input is not valid UTF-8"),
+            Error::GrammarLoad(e) => write!(f, "Error loading grammar: {}", e),
+            Error::QueryCompile(e) => write!(f, "Error creating query: {}", e),
+            Error::Parse => write!(f, "Error parsing input"),
+            Error::Verify(msg) => write!(f, "Verification failed: {}", msg),
+            Error::Config(msg) => write!(f, "Invalid configuration: {}", msg),
+            Error::Git(msg) => write!(f, "{}", msg),
+            Error::File { path, source } => write!(f, "'{}': {}", path, source),
+        }
+    }
+}
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Io(e) => Some(e),
+            Error::GrammarLoad(e) => Some(e),
+            Error::QueryCompile(e) => Some(e),
+            Error::File { source, .. } => Some(source.as_ref()),
+            _ => None,
+        }
+    }
+}
+impl From<clap::Error> for Error {
+    fn from(e: clap::Error) -> Self {
+        let message = e.render().to_string();
+        let message = message.strip_prefix("error: ").unwrap_or(&message);
+        Error::Usage(message.trim_end().to_string())
+    }
+}
+impl From<io::Error> for Error {
+    fn from(e: io::Error) -> Self {
+        Error::Io(e)
+    }
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn test_exit_codes_are_distinct() {
+        let errors = [
+            Error::Usage(String::new()),
+            Error::UnsupportedLanguage(String::new()),
+            Error::Io(io::Error::other("x")),
+            Error::InvalidUtf8,
+            Error::Parse,
+            Error::Verify(String::new()),
+            Error::Config(String::new()),
+            Error::Git(String::new()),
+        ];
+        let mut codes: Vec<i32> = errors.iter().map(|e| e.exit_code()).collect();
+        codes.push(EXIT_COMMENTS_FOUND);
+        codes.push(EXIT_SUCCESS);
+        codes.push(EXIT_UNPLACED_COMMENTS);
+        codes.push(EXIT_SECRETS_FOUND);
+        let len = codes.len();
+        codes.sort();
+        codes.dedup();
+        assert_eq!(codes.len(), len);
+    }
+    #[test]
+    fn test_file_context_keeps_root_kind() {
+        let err = Error::InvalidUtf8.in_file("a.py").in_file("b.py");
+        assert_eq!(err.path(), Some("a.py"));
+        assert_eq!(err.kind(), "invalid_utf8");
+        assert_eq!(err.exit_code(), EXIT_INVALID_UTF8);
+        assert_eq!(err.to_string(), "'a.py': input is not valid UTF-8");
+    }
+    #[test]
+    fn test_broken_pipe_is_detected_through_file_context() {
+        let err = Error::from(io::Error::from(io::ErrorKind::BrokenPipe)).in_file("a.py");
+        assert!(err.is_broken_pipe());
+        assert!(!Error::Io(io::Error::other("x")).is_broken_pipe());
+    }
+}
@@ -0,0 +1,190 @@
+use crate::archive::{ArchiveFormat, ArchiveWriter};
+use crate::error::{EXIT_SUCCESS, Error};
+use crate::git::{self, BlobReader};
+use crate::{
+    Args, StripOptions, TreeSitterLanguage, generated, parse_language, process_source,
+    resolve_language, walk,
+};
+use std::fs;
+use std::path::{Path, PathBuf};
+#[derive(clap::Args, Debug)]
+pub struct ExportArgs {
+    #[arg(long, value_name = "REV")]
+    pub rev: String,
+    #[arg(long, value_name = "DIR|ARCHIVE")]
+    pub out: String,
+    #[arg(long)]
+    pub include_generated: bool,
+}
+enum Snapshot {
+    Dir(PathBuf),
+    Archive(ArchiveWriter, u64),
+}
+impl Snapshot {
+    fn create(out: &str, mtime: u64) -> Result<Snapshot, Error> {
+        if let Some(format) = ArchiveFormat::detect(out) {
+            return Ok(Snapshot::Archive(
+                ArchiveWriter::create(out, format)?,
+                mtime,
+            ));
+        }
+        let dir = PathBuf::from(out);
+        if dir.exists() && fs::read_dir(&dir)?.next().is_some() {
+            return Err(Error::Usage(format!(
+                "output directory '{}' is not empty",
+                out
+            )));
+        }
+        fs::create_dir_all(&dir).map_err(|e| Error::from(e).in_file(out))?;
+        Ok(Snapshot::Dir(dir))
+    }
+    fn add_file(&mut self, path: &str, data: &[u8], executable: bool) -> Result<(), Error> {
+        let mode = if executable { 0o755 } else { 0o644 };
+        match self {
+            Snapshot::Dir(dir) => {
+                let target = dir.join(path);
+                create_parent(&target)?;
+                fs::write(&target, data)?;
+                #[cfg(unix)]
+                {
+                    use std::os::unix::fs::PermissionsExt;
+                    fs::set_permissions(&target, fs::Permissions::from_mode(mode))?;
+                }
+            }
+            Snapshot::Archive(writer, mtime) => writer.add_file(path, data, mode, *mtime)?,
+        }
+        Ok(())
+    }
+    fn add_symlink(&mut self, path: &str, target: &str) -> Result<(), Error> {
+        match self {
+            Snapshot::Dir(dir) => {
+                let link = dir.join(path);
+                create_parent(&link)?;
+                #[cfg(unix)]
+                std::os::unix::fs::symlink(target, &link)?;
+                #[cfg(not(unix))]
+                fs::write(&link, target)?;
+            }
+            Snapshot::Archive(writer, mtime) => writer.add_symlink(path, target, *mtime)?,
+        }
+        Ok(())
+    }
+    fn finish(self) -> Result<(), Error> {
+        if let Snapshot::Archive(writer, _) = self {
+            writer.finish()?;
+        }
+        Ok(())
+    }
+}
+fn create_parent(path: &Path) -> Result<(), Error> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    Ok(())
+}
+pub fn convert(
+    path: &str,
+    data: &[u8],
+    language_override: Option<TreeSitterLanguage>,
+    options: &StripOptions,
+    include_generated: bool,
+) -> Result<Option<String>, Error> {
+    if !walk::is_wanted(path, language_override) {
+        return Ok(None);
+    }
+    let language = resolve_language(path, language_override)?;
+    let input = std::str::from_utf8(data).map_err(|_| Error::InvalidUtf8)?;
+    if !include_generated && generated::is_generated(input) {
+        return Ok(None);
+    }
+    Ok(Some(
+        process_source(input, language, options, None)?.output.text,
+    ))
+}
+pub fn run(args: &ExportArgs, cli: &Args) -> Result<i32, Error> {
+    let language = cli.language.as_deref().map(parse_language).transpose()?;
+    let options = StripOptions::new(cli)?;
+    git::verify_revision(&args.rev)?;
+    let mtime = git::git(&["show", "-s", "--format=%ct", &args.rev])?
+        .parse()
+        .unwrap_or(0);
+    let entries = git::tree_entries(&args.rev)?;
+    let mut blobs = BlobReader::new()?;
+    let mut snapshot = Snapshot::create(&args.out, mtime)?;
+    let mut first_failure = None;
+    let mut stripped = 0;
+    let mut copied = 0;
+    for entry in &entries {
+        if entry.kind != "blob" {
+            eprintln!("{}: skipped {} entry", entry.path, entry.kind);
+            continue;
+        }
+        let data = blobs.read(&entry.object)?;
+        if entry.is_symlink() {
+            snapshot.add_symlink(&entry.path, &String::from_utf8_lossy(&data))?;
+            copied += 1;
+            continue;
+        }
+        let converted = match convert(
+            &entry.path,
+            &data,
+            language,
+            &options,
+            args.include_generated,
+        ) {
+            Ok(converted) => converted,
+            Err(e) => {
+                let e = e.in_file(&entry.path);
+                if !cli.force {
+                    return Err(e);
+                }
+                e.report(cli.error_format);
+                first_failure.get_or_insert(e.exit_code());
+                None
+            }
+        };
+        match converted {
+            Some(text) => {
+                snapshot.add_file(&entry.path, text.as_bytes(), entry.is_executable())?;
+                stripped += 1;
+            }
+            None => {
+                snapshot.add_file(&entry.path, &data, entry.is_executable())?;
+                copied += 1;
+            }
+        }
+    }
+    snapshot.finish()?;
+    eprintln!(
+        "Exported {} file(s) from {} to {}: {} stripped, {} copied verbatim",
+        stripped + copied,
+        args.rev,
+        args.out,
+        stripped,
+        copied
+    );
+    Ok(first_failure.unwrap_or(EXIT_SUCCESS))
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    #[cfg(feature = "python")]
+    fn test_convert_strips_recognized_files_only() {
+        use clap::Parser;
+        let args = Args::parse_from(["rmcm", "--no-config", "x"]);
+        let options = StripOptions::new(&args).unwrap();
+        let converted = convert("src/a.py", b"x = 1  # c\n", None, &options, false).unwrap();
+        assert_eq!(converted.as_deref(), Some("x = 1  \n"));
+        let verbatim = convert("README.md", b"# title\n", None, &options, false).unwrap();
+        assert_eq!(verbatim, None);
+        assert!(convert("a.py", b"\xff", None, &options, false).is_err());
+    }
+    #[test]
+    fn test_tree_entry_parse() {
+        let entry = git::TreeEntry::parse("120000 blob 0123abcd\tbin/tool").unwrap();
+        assert!(entry.is_symlink());
+        assert_eq!(entry.path, "bin/tool");
+        assert_eq!(entry.object, "0123abcd");
+    }
+}
@@ -0,0 +1,151 @@
+use crate::error::Error;
+use crate::filter::FilterArgs;
+use crate::{Args, Comment, TreeSitterLanguage, find_comments, for_each_source, load_filter};
+use std::io::{self, Write};
+#[derive(Debug, Clone, Copy, PartialEq, clap::ValueEnum)]
+pub enum ExtractFormat {
+    Text,
+    Jsonl,
+    Csv,
+}
+#[derive(clap::Args, Debug)]
+pub struct ExtractArgs {
+    #[arg(value_name = "PATHS")]
+    pub paths: Vec<String>,
+    #[arg(long, value_enum, value_name = "FORMAT", default_value = "text")]
+    pub format: ExtractFormat,
+    #[command(flatten)]
+    pub filter: FilterArgs,
+}
+const CSV_HEADER: &str =
+    "file,language,kind,kinds,start_byte,end_byte,start_line,start_column,end_line,end_column,text";
+pub fn run(args: &ExtractArgs, cli: &Args) -> Result<i32, Error> {
+    let stdout = io::stdout();
+    let mut out = stdout.lock();
+    if args.format == ExtractFormat::Csv {
+        writeln!(out, "{}", CSV_HEADER)?;
+    }
+    let filter = load_filter(&args.filter, cli)?;
+    let code = for_each_source(&args.paths, cli, |path, language, input| {
+        for comment in filter.apply(find_comments(input, language)?, input) {
+            write_comment(&mut out, args.format, path, language, input, &comment)?;
+        }
+        Ok(())
+    })?;
+    out.flush()?;
+    Ok(code)
+}
+fn write_comment(
+    out: &mut impl Write,
+    format: ExtractFormat,
+    path: &str,
+    language: TreeSitterLanguage,
+    input: &str,
+    comment: &Comment,
+) -> io::Result<()> {
+    let text = comment.text(input);
+    match format {
+        ExtractFormat::Text => writeln!(
+            out,
+            "{}:{}:{}: {} {}..{}: {}: {}",
+            path,
+            comment.start.row + 1,
+            comment.start.column + 1,
+            language.name(),
+            comment.range.start,
+            comment.range.end,
+            comment.kinds.names().join(","),
+            text.replace('\\', "\\\\").replace('\n', "\\n")
+        ),
+        ExtractFormat::Jsonl => {
+            let value = serde_json::json!({
+                "file": path,
+                "language": language.name(),
+                "kind": comment.kinds.primary().as_str(),
+                "kinds": comment.kinds.names(),
+                "start_byte": comment.range.start,
+                "end_byte": comment.range.end,
+                "start_line": comment.start.row + 1,
+                "start_column": comment.start.column + 1,
+                "end_line": comment.end.row + 1,
+                "end_column": comment.end.column + 1,
+                "text": text,
+            });
+            writeln!(out, "{}", value)
+        }
+        ExtractFormat::Csv => writeln!(
+            out,
+            "{},{},{},{},{},{},{},{},{},{},{}",
+            csv_field(path),
+            language.name(),
+            comment.kinds.primary().as_str(),
+            csv_field(&comment.kinds.names().join(",")),
+            comment.range.start,
+            comment.range.end,
+            comment.start.row + 1,
+            comment.start.column + 1,
+            comment.end.row + 1,
+            comment.end.column + 1,
+            csv_field(text)
+        ),
+    }
+}
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn test_csv_field_quoting() {
+        assert_eq!(csv_field("plain"), "plain");
+        assert_eq!(csv_field("a,b"), "\"a,b\"");
+        assert_eq!(csv_field("say \"hi\"\nbye"), "\"say \"\"hi\"\"\nbye\"");
+    }
+    #[test]
+    #[cfg(feature = "python")]
+    fn test_jsonl_record() {
+        let input = "x = 1\n# note\n";
+        let comments = find_comments(input, TreeSitterLanguage::Python).unwrap();
+        let mut out = Vec::new();
+        write_comment(
+            &mut out,
+            ExtractFormat::Jsonl,
+            "a.py",
+            TreeSitterLanguage::Python,
+            input,
+            &comments[0],
+        )
+        .unwrap();
+        let value: serde_json::Value = serde_json::from_slice(&out).unwrap();
+        assert_eq!(value["start_line"], 2);
+        assert_eq!(value["start_byte"], 6);
+        assert_eq!(value["kind"], "line");
+        assert_eq!(value["kinds"], serde_json::json!(["line", "leading"]));
+        assert_eq!(value["text"], "# note");
+    }
+    #[test]
+    #[cfg(feature = "python")]
+    fn test_text_record() {
+        let input = "x = 1\n# a\\b\n";
+        let comments = find_comments(input, TreeSitterLanguage::Python).unwrap();
+        let mut out = Vec::new();
+        write_comment(
+            &mut out,
+            ExtractFormat::Text,
+            "a.py",
+            TreeSitterLanguage::Python,
+            input,
+            &comments[0],
+        )
+        .unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "a.py:2:1: python 6..11: line,leading: # a\\\\b\n"
+        );
+    }
+}
@@ -0,0 +1,164 @@
+use crate::Comment;
+use crate::classify::{CommentKind, CommentKinds, comment_body};
+use crate::config::Config;
+use crate::error::Error;
+use regex::Regex;
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MatchOn {
+    Text,
+    Body,
+}
+#[derive(clap::Args, Debug, Clone, Default)]
+pub struct FilterArgs {
+    #[arg(long, value_enum, value_name = "KINDS", value_delimiter = ',')]
+    pub remove: Vec<CommentKind>,
+    #[arg(long, value_enum, value_name = "KINDS", value_delimiter = ',')]
+    pub keep: Vec<CommentKind>,
+    #[arg(long)]
+    pub keep_todos: bool,
+    #[arg(long, value_name = "REGEX", value_parser = Regex::new)]
+    pub keep_matching: Vec<Regex>,
+    #[arg(long, value_name = "REGEX", value_parser = Regex::new)]
+    pub only_matching: Vec<Regex>,
+    #[arg(long, value_enum, value_name = "WHAT")]
+    pub match_on: Option<MatchOn>,
+}
+#[derive(Debug, Clone, Default)]
+pub struct CommentFilter {
+    remove: Option<CommentKinds>,
+    keep: CommentKinds,
+    keep_matching: Vec<Regex>,
+    only_matching: Vec<Regex>,
+    match_on: Option<MatchOn>,
+}
+impl CommentFilter {
+    pub fn new(args: &FilterArgs, config: &Config) -> Result<Self, Error> {
+        let mut keep: CommentKinds = args.keep.iter().chain(&config.keep).copied().collect();
+        if args.keep_todos || config.keep_todos {
+            keep.insert(CommentKind::Todo);
+        }
+        let remove: Vec<CommentKind> = args.remove.iter().chain(&config.remove).copied().collect();
+        let compile = |patterns: &[String]| -> Result<Vec<Regex>, Error> {
+            patterns
+                .iter()
+                .map(|p| Regex::new(p).map_err(|e| Error::Config(e.to_string())))
+                .collect()
+        };
+        let mut keep_matching = compile(&config.keep_matching)?;
+        keep_matching.extend(args.keep_matching.iter().cloned());
+        let mut only_matching = compile(&config.only_matching)?;
+        only_matching.extend(args.only_matching.iter().cloned());
+        Ok(CommentFilter {
+            remove: (!remove.is_empty()).then(|| remove.into_iter().collect()),
+            keep,
+            keep_matching,
+            only_matching,
+            match_on: args.match_on.or(config.match_on),
+        })
+    }
+    pub fn should_remove(&self, comment: &Comment, input: &str) -> bool {
+        if comment.kinds.intersects(self.keep) {
+            return false;
+        }
+        let text = comment.text(input);
+        let subject = match self.match_on.unwrap_or(MatchOn::Text) {
+            MatchOn::Text => std::borrow::Cow::Borrowed(text),
+            MatchOn::Body => std::borrow::Cow::Owned(comment_body(text)),
+        };
+        if self.keep_matching.iter().any(|re| re.is_match(&subject)) {
+            return false;
+        }
+        if self
+            .remove
+            .is_some_and(|remove| !comment.kinds.intersects(remove))
+        {
+            return false;
+        }
+        self.only_matching.is_empty() || self.only_matching.iter().any(|re| re.is_match(&subject))
+    }
+    pub fn apply(&self, comments: Vec<Comment>, input: &str) -> Vec<Comment> {
+        comments
+            .into_iter()
+            .filter(|c| self.should_remove(c, input))
+            .collect()
+    }
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::classify::classify;
+    fn comment(input: &str) -> Comment {
+        Comment {
+            range: 0..input.len(),
+            start: tree_sitter::Point::default(),
+            end: tree_sitter::Point::default(),
+            kinds: classify(input, 0..input.len(), usize::MAX),
+        }
+    }
+    fn removes(filter: &CommentFilter, text: &str) -> bool {
+        filter.should_remove(&comment(text), text)
+    }
+    #[test]
+    fn test_remove_limits_and_keep_wins() {
+        let args = FilterArgs {
+            remove: vec![CommentKind::Block, CommentKind::Banner],
+            keep: vec![CommentKind::Doc],
+            ..FilterArgs::default()
+        };
+        let filter = CommentFilter::new(&args, &Config::default()).unwrap();
+        assert!(removes(&filter, "/* block */"));
+        assert!(removes(&filter, "// =========="));
+        assert!(!removes(&filter, "// line"));
+        assert!(!removes(&filter, "/** doc block */"));
+    }
+    #[test]
+    fn test_default_removes_everything() {
+        let filter = CommentFilter::default();
+        assert!(removes(&filter, "// line"));
+        assert!(removes(&filter, "/// doc"));
+    }
+    #[test]
+    fn test_keep_matching_beats_only_matching() {
+        let args = FilterArgs {
+            keep_matching: vec![Regex::new("KEEP").unwrap()],
+            only_matching: vec![Regex::new("(?i)generated by").unwrap()],
+            ..FilterArgs::default()
+        };
+        let filter = CommentFilter::new(&args, &Config::default()).unwrap();
+        assert!(removes(&filter, "// Generated by protoc"));
+        assert!(!removes(&filter, "// Generated by protoc, KEEP"));
+        assert!(!removes(&filter, "// hand written"));
+    }
+    #[test]
+    fn test_match_on_body_strips_markers() {
+        let args = FilterArgs {
+            only_matching: vec![Regex::new("^x").unwrap()],
+            ..FilterArgs::default()
+        };
+        let mut config = Config::default();
+        assert!(!removes(
+            &CommentFilter::new(&args, &config).unwrap(),
+            "# x = 1"
+        ));
+        config.match_on = Some(MatchOn::Body);
+        assert!(removes(
+            &CommentFilter::new(&args, &config).unwrap(),
+            "# x = 1"
+        ));
+    }
+    #[test]
+    fn test_config_rules_merge_with_args() {
+        let config = Config::parse("keep_matching = [\"@copyright\"]").unwrap();
+        let args = FilterArgs {
+            keep_matching: vec![Regex::new("KEEP").unwrap()],
+            ..FilterArgs::default()
+        };
+        let filter = CommentFilter::new(&args, &config).unwrap();
+        assert!(!removes(&filter, "// @copyright Acme"));
+        assert!(!removes(&filter, "// KEEP"));
+        assert!(removes(&filter, "// other"));
+        let bad = Config::parse("only_matching = [\"(\"]").unwrap();
+        assert!(CommentFilter::new(&FilterArgs::default(), &bad).is_err());
+    }
+}
@@ -0,0 +1,49 @@
+use regex::Regex;
+use std::sync::LazyLock;
+const HEADER_LINES: usize = 40;
+const COMMENT_PREFIXES: &[&str] = &["//", "#", "/*", "*", "--", "<!--", ";", "%"];
+static GENERATED_MARKERS: LazyLock<Vec<Regex>> = LazyLock::new(|| {
+    [
+        r"^// Code generated .* DO NOT EDIT\.$",
+        r"@generated\b",
+        r"<auto-generated\b",
+        r"(?i)\bthis file (is|was) (auto-?generated|automatically generated)\b",
+    ]
+    .iter()
+    .map(|p| Regex::new(p).unwrap())
+    .collect()
+});
+pub fn is_generated(input: &str) -> bool {
+    input
+        .lines()
+        .take(HEADER_LINES)
+        .map(str::trim)
+        .filter(|line| COMMENT_PREFIXES.iter().any(|p| line.starts_with(p)))
+        .any(|line| GENERATED_MARKERS.iter().any(|re| re.is_match(line)))
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn test_standard_markers() {
+        assert!(is_generated(
+            "// Code generated by protoc-gen-go. DO NOT EDIT.\npackage pb\n"
+        ));
+        assert!(is_generated("/*\n * @generated SignedSource<<abc>>\n */\n"));
+        assert!(is_generated(
+            "// <auto-generated>\n//     This code was generated by a tool.\n// </auto-generated>\n"
+        ));
+        assert!(is_generated(
+            "#\n# This file is autogenerated by pip-compile with Python 3.12\n#\n"
+        ));
+    }
+    #[test]
+    fn test_markers_outside_comments_or_header_are_ignored() {
+        assert!(!is_generated(
+            "// Code generated by hand, feel free to edit.\n"
+        ));
+        assert!(!is_generated("let s = \"@generated\";\n"));
+        let late = format!("{}// @generated\n", "x = 1\n".repeat(HEADER_LINES));
+        assert!(!is_generated(&late));
+    }
+}
@@ -0,0 +1,399 @@
+use crate::error::{EXIT_SUCCESS, Error};
+use crate::{
+    Args, SOURCE_EXTENSIONS, StripOptions, Stripped, TreeSitterLanguage, detect_language,
+    parse_language, process_source, resolve_language, walk,
+};
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Child, ChildStdin, ChildStdout, Command, Stdio};
+const DRIVER: &str = "rmcm";
+#[derive(clap::Args, Debug)]
+pub struct TextconvArgs {
+    #[arg(value_name = "PATH")]
+    pub path: String,
+}
+#[derive(clap::Args, Debug)]
+pub struct GitSetupArgs {
+    #[arg(
+        long,
+        help = "Write patterns to .git/info/attributes instead of .gitattributes"
+    )]
+    pub private: bool,
+    #[arg(long, value_name = "COMMAND", default_value = "rmcm")]
+    pub command: String,
+    #[arg(
+        long,
+        help = "Let git cache converted blobs in notes (needs a committer identity)"
+    )]
+    pub cache: bool,
+}
+fn spawn_error(e: io::Error) -> Error {
+    if e.kind() == io::ErrorKind::NotFound {
+        Error::Git("git executable not found".to_string())
+    } else {
+        Error::Io(e)
+    }
+}
+pub fn git(args: &[&str]) -> Result<String, Error> {
+    let output = Command::new("git")
+        .args(args)
+        .output()
+        .map_err(spawn_error)?;
+    if !output.status.success() {
+        return Err(Error::Git(format!(
+            "git {} failed: {}",
+            args.join(" "),
+            String::from_utf8_lossy(&output.stderr).trim()
+        )));
+    }
+    String::from_utf8(output.stdout)
+        .map(|s| s.trim_end_matches('\n').to_string())
+        .map_err(|_| Error::InvalidUtf8)
+}
+#[derive(Debug, Clone, PartialEq)]
+pub struct TreeEntry {
+    pub mode: String,
+    pub kind: String,
+    pub object: String,
+    pub path: String,
+}
+impl TreeEntry {
+    pub fn parse(line: &str) -> Option<TreeEntry> {
+        let (meta, path) = line.split_once('\t')?;
+        let mut fields = meta.split(' ');
+        Some(TreeEntry {
+            mode: fields.next()?.to_string(),
+            kind: fields.next()?.to_string(),
+            object: fields.next()?.to_string(),
+            path: path.to_string(),
+        })
+    }
+    pub fn is_symlink(&self) -> bool {
+        self.mode == "120000"
+    }
+    pub fn is_executable(&self) -> bool {
+        self.mode == "100755"
+    }
+}
+pub fn tree_entries(rev: &str) -> Result<Vec<TreeEntry>, Error> {
+    git(&["ls-tree", "-r", "-z", "--full-tree", rev])?
+        .split('\0')
+        .filter(|line| !line.is_empty())
+        .map(|line| {
+            TreeEntry::parse(line)
+                .ok_or_else(|| Error::Git(format!("unexpected git ls-tree output: {}", line)))
+        })
+        .collect()
+}
+pub struct BlobReader {
+    child: Child,
+    stdin: Option<ChildStdin>,
+    stdout: BufReader<ChildStdout>,
+}
+impl BlobReader {
+    pub fn new() -> Result<Self, Error> {
+        let mut child = Command::new("git")
+            .args(["cat-file", "--batch"])
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn()
+            .map_err(spawn_error)?;
+        let stdin = child.stdin.take();
+        let stdout = BufReader::new(child.stdout.take().expect("piped stdout"));
+        Ok(BlobReader {
+            child,
+            stdin,
+            stdout,
+        })
+    }
+    pub fn read(&mut self, object: &str) -> Result<Vec<u8>, Error> {
+        let stdin = self.stdin.as_mut().expect("open stdin");
+        writeln!(stdin, "{}", object)?;
+        stdin.flush()?;
+        let mut header = String::new();
+        self.stdout.read_line(&mut header)?;
+        let size = header
+            .split(' ')
+            .nth(2)
+            .and_then(|size| size.trim().parse::<usize>().ok())
+            .ok_or_else(|| Error::Git(format!("git object not found: {}", object)))?;
+        let mut data = vec![0; size + 1];
+        self.stdout.read_exact(&mut data)?;
+        data.pop();
+        Ok(data)
+    }
+}
+impl Drop for BlobReader {
+    fn drop(&mut self) {
+        self.stdin.take();
+        let _ = self.child.wait();
+    }
+}
+fn strip(path: &str, input: &str, cli: &Args) -> Result<Stripped, Error> {
+    let language = cli.language.as_deref().map(parse_language).transpose()?;
+    let language = resolve_language(path, language)?;
+    process_source(input, language, &StripOptions::new(cli)?, None)
+}
+pub fn verify_revision(rev: &str) -> Result<(), Error> {
+    git(&[
+        "rev-parse",
+        "--verify",
+        "--quiet",
+        &format!("{}^{{commit}}", rev),
+    ])
+    .map(|_| ())
+    .map_err(|_| Error::Git(format!("unknown git revision '{}'", rev)))
+}
+pub struct Baseline {
+    rev: String,
+    root: PathBuf,
+    renames: HashMap<PathBuf, String>,
+    added: HashSet<PathBuf>,
+}
+fn parse_changes(output: &str) -> (HashMap<PathBuf, String>, HashSet<PathBuf>) {
+    let mut renames = HashMap::new();
+    let mut added = HashSet::new();
+    let mut fields = output.split('\0').filter(|f| !f.is_empty());
+    while let (Some(status), Some(path)) = (fields.next(), fields.next()) {
+        if status.starts_with(['R', 'C']) {
+            let Some(new) = fields.next() else {
+                break;
+            };
+            if status.starts_with('R') {
+                renames.insert(PathBuf::from(new), path.to_string());
+            } else {
+                added.insert(PathBuf::from(new));
+            }
+        } else if status == "A" {
+            added.insert(PathBuf::from(path));
+        }
+    }
+    (renames, added)
+}
+impl Baseline {
+    pub fn open(rev: &str) -> Result<Baseline, Error> {
+        verify_revision(rev)?;
+        let root = fs::canonicalize(git(&["rev-parse", "--show-toplevel"])?)?;
+        let (renames, added) =
+            parse_changes(&git(&["diff", "-M", "--name-status", "-z", rev, "--"])?);
+        Ok(Baseline {
+            rev: rev.to_string(),
+            root,
+            renames,
+            added,
+        })
+    }
+    pub fn file(&self, path: &str) -> Result<String, Error> {
+        let full = fs::canonicalize(path)?;
+        let relative = full
+            .strip_prefix(&self.root)
+            .map_err(|_| Error::Git(format!("'{}' is outside the git repository", path)))?;
+        if self.added.contains(relative) {
+            return Ok(String::new());
+        }
+        let old = match self.renames.get(relative) {
+            Some(old) => old.clone(),
+            None => relative.to_string_lossy().into_owned(),
+        };
+        let spec = format!("{}:{}", self.rev, old);
+        let output = Command::new("git")
+            .args(["cat-file", "blob", &spec])
+            .output()
+            .map_err(spawn_error)?;
+        if !output.status.success() {
+            return Err(Error::Git(format!(
+                "file is not tracked by git and does not exist at {}; stage it to treat it as a new file",
+                self.rev
+            )));
+        }
+        Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+    }
+}
+fn repo_paths(args: &[&str]) -> Result<Vec<String>, Error> {
+    let cdup = git(&["rev-parse", "--show-cdup"])?;
+    Ok(git(args)?
+        .split('\0')
+        .filter(|path| !path.is_empty())
+        .map(|path| format!("{}{}", cdup, path))
+        .collect())
+}
+pub fn changed_files(
+    since: Option<&str>,
+    pathspecs: &[String],
+    language: Option<TreeSitterLanguage>,
+) -> Result<Vec<String>, Error> {
+    let mut args = vec!["diff", "--name-only", "-z", "--diff-filter=ACMR"];
+    args.push(since.unwrap_or("--cached"));
+    args.push("--");
+    args.extend(pathspecs.iter().map(String::as_str));
+    Ok(repo_paths(&args)?
+        .into_iter()
+        .filter(|path| walk::is_wanted(path, language))
+        .collect())
+}
+pub fn index_spec(path: &str) -> String {
+    if path.starts_with("../") {
+        format!(":{}", path)
+    } else {
+        format!(":./{}", path)
+    }
+}
+pub fn check_fully_staged(files: &[String]) -> Result<(), Error> {
+    let unstaged: HashSet<PathBuf> = repo_paths(&["diff", "--name-only", "-z"])?
+        .into_iter()
+        .filter_map(|path| fs::canonicalize(path).ok())
+        .collect();
+    let partial: Vec<&str> = files
+        .iter()
+        .filter(|path| fs::canonicalize(path).is_ok_and(|p| unstaged.contains(&p)))
+        .map(String::as_str)
+        .collect();
+    if partial.is_empty() {
+        return Ok(());
+    }
+    Err(Error::Usage(format!(
+        "refusing to rewrite files with unstaged changes: {}; stage or stash them first",
+        partial.join(", ")
+    )))
+}
+pub fn restage(rewritten: &[String]) -> Result<(), Error> {
+    if rewritten.is_empty() {
+        return Ok(());
+    }
+    let mut paths = vec!["add", "--"];
+    paths.extend(rewritten.iter().map(String::as_str));
+    git(&paths).map(|_| ())
+}
+pub fn textconv(path: &str, input: &[u8], cli: &Args) -> Vec<u8> {
+    match std::str::from_utf8(input) {
+        Ok(text) => match strip(path, text, cli) {
+            Ok(stripped) => tidy(text, &stripped).into_bytes(),
+            Err(_) => input.to_vec(),
+        },
+        Err(_) => input.to_vec(),
+    }
+}
+fn tidy(input: &str, stripped: &Stripped) -> String {
+    let line_starts: Vec<usize> = std::iter::once(0)
+        .chain(input.match_indices('\n').map(|(i, _)| i + 1))
+        .collect();
+    let line_of = |offset: usize| line_starts.partition_point(|&start| start <= offset) - 1;
+    let touched: HashSet<usize> = stripped
+        .removed
+        .iter()
+        .flat_map(|c| c.start.row..=c.end.row)
+        .collect();
+    let output = &stripped.output;
+    let mut result = String::with_capacity(output.text.len());
+    let mut offset = 0;
+    for line in output.text.split_inclusive('\n') {
+        let start = offset;
+        offset += line.len();
+        if !touched.contains(&line_of(output.origins[start])) {
+            result.push_str(line);
+            continue;
+        }
+        let code = line.trim_end();
+        if !code.is_empty() {
+            result.push_str(code);
+            result.push_str(&line[line.trim_end_matches(['\r', '\n']).len()..]);
+        }
+    }
+    result
+}
+pub fn run_textconv(args: &TextconvArgs, cli: &Args) -> Result<i32, Error> {
+    let input = fs::read(&args.path).map_err(|e| Error::from(e).in_file(&args.path))?;
+    let mut stdout = io::stdout();
+    stdout.write_all(&textconv(&args.path, &input, cli))?;
+    stdout.flush()?;
+    Ok(EXIT_SUCCESS)
+}
+pub fn attribute_lines() -> Vec<String> {
+    SOURCE_EXTENSIONS
+        .iter()
+        .filter(|ext| detect_language(&format!("file.{}", ext)).is_some())
+        .map(|ext| format!("*.{} diff={}", ext, DRIVER))
+        .collect()
+}
+pub fn run_setup(args: &GitSetupArgs) -> Result<i32, Error> {
+    let path = if args.private {
+        git(&["rev-parse", "--git-path", "info/attributes"])?
+    } else {
+        format!("{}/.gitattributes", git(&["rev-parse", "--show-toplevel"])?)
+    };
+    let existing = match fs::read_to_string(&path) {
+        Ok(text) => text,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => String::new(),
+        Err(e) => return Err(Error::from(e).in_file(&path)),
+    };
+    let missing: Vec<String> = attribute_lines()
+        .into_iter()
+        .filter(|line| !existing.lines().any(|l| l.trim() == line))
+        .collect();
+    if !missing.is_empty() {
+        let mut text = existing;
+        if !text.is_empty() && !text.ends_with('\n') {
+            text.push('\n');
+        }
+        text.push_str("# hide comment-only changes in git diff (rmcm git-setup)\n");
+        for line in &missing {
+            text.push_str(line);
+            text.push('\n');
+        }
+        if let Some(parent) = Path::new(&path).parent() {
+            fs::create_dir_all(parent).map_err(|e| Error::from(e).in_file(&path))?;
+        }
+        fs::write(&path, text).map_err(|e| Error::from(e).in_file(&path))?;
+    }
+    println!("Added {} pattern(s) to {}", missing.len(), path);
+    let textconv = format!("{} textconv", args.command);
+    git(&[
+        "config",
+        "--local",
+        &format!("diff.{}.textconv", DRIVER),
+        &textconv,
+    ])?;
+    git(&[
+        "config",
+        "--local",
+        "--bool",
+        &format!("diff.{}.cachetextconv", DRIVER),
+        if args.cache { "true" } else { "false" },
+    ])?;
+    println!("Set diff.{}.textconv = {}", DRIVER, textconv);
+    Ok(EXIT_SUCCESS)
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::Parser;
+    #[test]
+    #[cfg(feature = "python")]
+    fn test_textconv_strips_known_files() {
+        let args = Args::parse_from(["rmcm", "--no-config", "x.py"]);
+        let output = textconv("x.py", b"# note\nx = 1  # one\n\ny = 2\n", &args);
+        assert_eq!(output, b"x = 1\n\ny = 2\n");
+    }
+    #[test]
+    fn test_textconv_passes_through_unknown_files() {
+        let args = Args::parse_from(["rmcm", "--no-config", "x"]);
+        assert_eq!(textconv("notes.txt", b"# hi\n", &args), b"# hi\n");
+        assert_eq!(textconv("x.py", b"\xff# hi\n", &args), b"\xff# hi\n");
+    }
+    #[test]
+    fn test_index_spec() {
+        assert_eq!(index_spec("src/a.rs"), ":./src/a.rs");
+        assert_eq!(index_spec("../lib/b.py"), ":../lib/b.py");
+    }
+    #[test]
+    fn test_parse_changes() {
+        let (renames, added) =
+            parse_changes("M\0src/a.rs\0R087\0old/b.rs\0src/b.rs\0A\0src/c.rs\0D\0src/d.rs\0");
+        assert_eq!(renames.len(), 1);
+        assert_eq!(renames[Path::new("src/b.rs")], "old/b.rs");
+        assert_eq!(added.len(), 1);
+        assert!(added.contains(Path::new("src/c.rs")));
+    }
+}
@@ -0,0 +1,150 @@
+use crate::error::Error;
+use crate::{Args, TreeSitterLanguage, for_each_source, parse_source, remove_comments_treesitter};
+use sha2::{Digest, Sha256};
+use std::io::{self, Write};
+const VERBATIM_GAP_KINDS: &[&str] = &["string", "char", "heredoc", "template", "raw_text"];
+#[derive(Debug, Clone, Copy, PartialEq, clap::ValueEnum)]
+pub enum HashAlgorithm {
+    Sha256,
+    Blake3,
+}
+#[derive(clap::Args, Debug)]
+pub struct HashArgs {
+    #[arg(value_name = "PATHS")]
+    pub paths: Vec<String>,
+    #[arg(long, value_enum, value_name = "ALGORITHM", default_value = "sha256")]
+    pub algorithm: HashAlgorithm,
+    #[arg(long)]
+    pub aggregate: bool,
+}
+pub fn digest(algorithm: HashAlgorithm, data: &[u8]) -> String {
+    let bytes: Vec<u8> = match algorithm {
+        HashAlgorithm::Sha256 => Sha256::digest(data).to_vec(),
+        HashAlgorithm::Blake3 => blake3::hash(data).as_bytes().to_vec(),
+    };
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+pub fn normalized_code(input: &str, language: TreeSitterLanguage) -> Result<String, Error> {
+    let stripped = remove_comments_treesitter(input, language)?;
+    let tree = parse_source(&stripped, language)?;
+    let mut leaves = Vec::new();
+    let mut cursor = tree.walk();
+    let mut descend = true;
+    loop {
+        let node = cursor.node();
+        if descend && cursor.goto_first_child() {
+            continue;
+        }
+        if descend
+            && node.child_count() == 0
+            && !node.is_extra()
+            && !stripped[node.byte_range()].trim().is_empty()
+        {
+            leaves.push(node.byte_range());
+        }
+        if cursor.goto_next_sibling() {
+            descend = true;
+        } else if cursor.goto_parent() {
+            descend = false;
+        } else {
+            break;
+        }
+    }
+    let root = tree.root_node();
+    let mut normalized = String::with_capacity(stripped.len());
+    let mut last = 0;
+    for range in leaves {
+        if range.start < last {
+            continue;
+        }
+        let gap = &stripped[last..range.start];
+        let verbatim = !gap.trim().is_empty()
+            || root
+                .descendant_for_byte_range(last, range.start)
+                .is_some_and(|n| VERBATIM_GAP_KINDS.iter().any(|k| n.kind().contains(k)));
+        if verbatim || last == 0 {
+            normalized.push_str(if last == 0 { gap.trim_start() } else { gap });
+        } else if gap.contains('\n') {
+            normalized.push('\n');
+            normalized.push_str(&gap[gap.rfind('\n').unwrap() + 1..]);
+        } else if !gap.is_empty() {
+            normalized.push(' ');
+        }
+        normalized.push_str(&stripped[range.clone()]);
+        last = range.end;
+    }
+    normalized.push('\n');
+    Ok(normalized.replace("\r\n", "\n"))
+}
+pub fn run(args: &HashArgs, cli: &Args) -> Result<i32, Error> {
+    let stdout = io::stdout();
+    let mut out = stdout.lock();
+    let mut entries = Vec::new();
+    let code = for_each_source(&args.paths, cli, |path, language, input| {
+        let code = normalized_code(input, language)?;
+        let hash = digest(args.algorithm, code.as_bytes());
+        if !args.aggregate {
+            writeln!(out, "{}  {}", hash, path)?;
+        }
+        entries.push((path.to_string(), hash));
+        Ok(())
+    })?;
+    if args.aggregate {
+        entries.sort();
+        let manifest: String = entries
+            .iter()
+            .map(|(path, hash)| format!("{}  {}\n", hash, path))
+            .collect();
+        writeln!(out, "{}", digest(args.algorithm, manifest.as_bytes()))?;
+    }
+    Ok(code)
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    #[cfg(feature = "rust-lang")]
+    fn test_comment_and_whitespace_changes_hash_the_same() {
+        let lang = TreeSitterLanguage::Rust;
+        let a = normalized_code("fn f() {\n    let x = a + b;\n}\n", lang).unwrap();
+        let b = normalized_code(
+            "// doc\nfn f() {\n\n    let x = a /* sum */ + b;   // trailing\r\n}",
+            lang,
+        )
+        .unwrap();
+        assert_eq!(a, b);
+        assert_eq!(a, "fn f() {\n    let x = a + b;\n}\n");
+        let c = normalized_code("fn f() {\n    let x = a - b;\n}\n", lang).unwrap();
+        assert_ne!(a, c);
+    }
+    #[test]
+    #[cfg(feature = "rust-lang")]
+    fn test_whitespace_inside_strings_is_kept() {
+        let lang = TreeSitterLanguage::Rust;
+        let a = normalized_code("let s = \"a  b\";", lang).unwrap();
+        let b = normalized_code("let s = \"a b\";", lang).unwrap();
+        assert_ne!(a, b);
+        let c = normalized_code("let s = \"  \";", lang).unwrap();
+        let d = normalized_code("let s = \" \";", lang).unwrap();
+        assert_ne!(c, d);
+    }
+    #[test]
+    #[cfg(feature = "python")]
+    fn test_python_indentation_matters() {
+        let lang = TreeSitterLanguage::Python;
+        let a = normalized_code("if x:\n    a()\nb()\n", lang).unwrap();
+        let b = normalized_code("if x:\n    a()\n    b()\n", lang).unwrap();
+        assert_ne!(a, b);
+    }
+    #[test]
+    fn test_digests() {
+        assert_eq!(
+            digest(HashAlgorithm::Sha256, b"abc"),
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+        assert_eq!(
+            digest(HashAlgorithm::Blake3, b"abc"),
+            "6437b3ac38465133ffb63b75273a8db548c558465d79db03fd359c6cd5bd9d85"
+        );
+    }
+}
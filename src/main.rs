@@ -1,9 +1,39 @@
-use clap::Parser;
+mod archive;
+mod baseline;
+mod bundle;
+mod classify;
+mod commented_code;
+mod commits;
+mod config;
+mod diff;
+mod docs;
+mod edits;
+mod error;
+mod export;
+mod extract;
+mod filter;
+mod generated;
+mod git;
+mod hash;
+mod markers;
+mod redact;
+mod restore;
+mod secrets;
+mod sourcemap;
+mod todo;
+mod walk;
+use clap::{Parser, Subcommand, ValueEnum};
+use classify::CommentKinds;
+use error::{EXIT_COMMENTS_FOUND, EXIT_SUCCESS, Error, ErrorFormat};
+use sourcemap::Traced;
 use std::fs;
 use std::io::{self, Read, Write};
-use std::path::Path;
+use std::ops::Range;
+use std::path::{Component, Path, PathBuf};
 use std::process;
-use tree_sitter::{Language, Parser as TSParser, Query, QueryCursor, StreamingIterator};
+use tree_sitter::{
+    Language, Parser as TSParser, Point, Query, QueryCursor, StreamingIterator, Tree,
+};
 #[derive(Debug, Clone, Copy, PartialEq)]
 enum TreeSitterLanguage {
     #[cfg(feature = "bash")]
@@ -44,6 +74,46 @@ enum TreeSitterLanguage {
     TypeScript,
 }
 impl TreeSitterLanguage {
+    fn name(&self) -> &'static str {
+        match self {
+            #[cfg(feature = "bash")]
+            TreeSitterLanguage::Bash => "bash",
+            #[cfg(feature = "c")]
+            TreeSitterLanguage::C => "c",
+            #[cfg(feature = "c-sharp")]
+            TreeSitterLanguage::CSharp => "c#",
+            #[cfg(feature = "cpp")]
+            TreeSitterLanguage::Cpp => "c++",
+            #[cfg(feature = "css")]
+            TreeSitterLanguage::Css => "css",
+            #[cfg(feature = "go")]
+            TreeSitterLanguage::Go => "go",
+            #[cfg(feature = "haskell")]
+            TreeSitterLanguage::Haskell => "haskell",
+            #[cfg(feature = "html")]
+            TreeSitterLanguage::Html => "html",
+            #[cfg(feature = "java")]
+            TreeSitterLanguage::Java => "java",
+            #[cfg(feature = "javascript")]
+            TreeSitterLanguage::JavaScript => "javascript",
+            #[cfg(feature = "lua")]
+            TreeSitterLanguage::Lua => "lua",
+            #[cfg(feature = "php")]
+            TreeSitterLanguage::Php => "php",
+            #[cfg(feature = "python")]
+            TreeSitterLanguage::Python => "python",
+            #[cfg(feature = "ruby")]
+            TreeSitterLanguage::Ruby => "ruby",
+            #[cfg(feature = "rust-lang")]
+            TreeSitterLanguage::Rust => "rust",
+            #[cfg(feature = "scala")]
+            TreeSitterLanguage::Scala => "scala",
+            #[cfg(feature = "swift")]
+            TreeSitterLanguage::Swift => "swift",
+            #[cfg(feature = "typescript")]
+            TreeSitterLanguage::TypeScript => "typescript",
+        }
+    }
     fn get_language(&self) -> Language {
         match self {
             #[cfg(feature = "bash")]
@@ -88,19 +158,167 @@ impl TreeSitterLanguage {
 #[derive(Parser, Debug)]
 #[command(name = "comment_remover")]
 #[command(about = "Remove comments from source code files using tree-sitter", long_about = None)]
+#[command(args_conflicts_with_subcommands = true)]
 struct Args {
+    #[command(subcommand)]
+    command: Option<Command>,
     #[arg(value_name = "FILES")]
     files: Vec<String>,
-    #[arg(short, long, value_name = "LANG")]
+    #[arg(short, long, value_name = "LANG", global = true)]
     language: Option<String>,
     #[arg(short, long)]
     in_place: bool,
     #[arg(short, long, value_name = "N")]
     collapse_whitespace: Option<usize>,
-    #[arg(short, long)]
+    #[arg(
+        long,
+        value_enum,
+        value_name = "MODE",
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = "bytes",
+        conflicts_with = "collapse_whitespace"
+    )]
+    blank: Option<BlankMode>,
+    #[arg(short, long, global = true)]
     force: bool,
+    #[arg(long, conflicts_with = "in_place")]
+    check: bool,
+    #[command(flatten)]
+    filter: filter::FilterArgs,
+    #[arg(long)]
+    verify: bool,
+    #[arg(long)]
+    strip_markers: bool,
+    #[arg(long)]
+    include_generated: bool,
+    #[arg(long, conflicts_with = "changed_since")]
+    staged: bool,
+    #[arg(long, value_name = "REF")]
+    changed_since: Option<String>,
+    #[arg(long, requires = "in_place")]
+    restage: bool,
+    #[arg(long, value_name = "REF")]
+    only_new_since: Option<String>,
+    #[arg(long, value_name = "DIR", conflicts_with_all = ["in_place", "check", "suffix"])]
+    out_dir: Option<String>,
+    #[arg(long, value_name = "SUFFIX", conflicts_with_all = ["in_place", "check"])]
+    suffix: Option<String>,
+    #[arg(long, requires = "out_dir")]
+    copy_other: bool,
+    #[arg(
+        long,
+        value_enum,
+        value_name = "FORMAT",
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = "text",
+        conflicts_with_all = ["in_place", "check", "out_dir", "suffix", "source_map"]
+    )]
+    bundle: Option<bundle::BundleFormat>,
+    #[arg(long, value_name = "TEMPLATE", requires = "bundle")]
+    bundle_header: Option<String>,
+    #[arg(long, requires = "bundle")]
+    bundle_totals: bool,
+    #[arg(
+        long,
+        value_enum,
+        value_name = "FORMAT",
+        default_value = "text",
+        conflicts_with_all = [
+            "in_place", "check", "out_dir", "suffix", "bundle", "source_map", "save_comments",
+            "blank", "redact", "collapse_whitespace", "verify"
+        ]
+    )]
+    format: edits::OutputFormat,
+    #[arg(
+        long,
+        value_name = "PATH",
+        num_args = 0..=1,
+        require_equals = true,
+        conflicts_with = "check"
+    )]
+    source_map: Option<Option<String>>,
+    #[arg(
+        long,
+        value_enum,
+        value_name = "FORMAT",
+        default_value = "v3",
+        requires = "source_map"
+    )]
+    source_map_format: sourcemap::SourceMapFormat,
+    #[arg(long, requires = "source_map")]
+    source_map_url: bool,
+    #[arg(long, requires = "source_map")]
+    source_map_sources_content: bool,
+    #[arg(long, value_name = "FILE", conflicts_with = "check")]
+    save_comments: Option<String>,
+    #[arg(long, conflicts_with_all = ["blank", "save_comments"])]
+    redact: bool,
+    #[arg(long, value_name = "REGEX", value_parser = regex::Regex::new, requires = "redact")]
+    redact_pattern: Vec<regex::Regex>,
+    #[arg(long, value_name = "PATH", global = true, conflicts_with = "no_config")]
+    config: Option<String>,
+    #[arg(long, global = true)]
+    no_config: bool,
+    #[arg(
+        long,
+        value_enum,
+        value_name = "FORMAT",
+        default_value = "text",
+        global = true
+    )]
+    error_format: ErrorFormat,
+}
+#[derive(Debug, Clone, Copy, PartialEq, clap::ValueEnum)]
+enum BlankMode {
+    Bytes,
+    Chars,
+}
+#[derive(Subcommand, Debug)]
+enum Command {
+    #[command(about = "List comments with their location, language and kind")]
+    Extract(extract::ExtractArgs),
+    #[command(about = "Print doc comments as JSON, keyed by the symbol they document")]
+    Docs(docs::DocsArgs),
+    #[command(about = "Report TODO, FIXME, HACK, XXX and BUG markers found in comments")]
+    Todos(todo::TodosArgs),
+    #[command(about = "Find or remove comments that contain commented-out code")]
+    CommentedCode(commented_code::CommentedCodeArgs),
+    #[command(about = "Put comments saved with --save-comments back into the code")]
+    Restore(restore::RestoreArgs),
+    #[command(about = "Scan comments for secrets and credentials")]
+    ScanSecrets(secrets::ScanSecretsArgs),
+    #[command(about = "Print a digest of each file's code, ignoring comments and whitespace")]
+    Hash(hash::HashArgs),
+    #[command(
+        about = "Show a unified diff of two files or directories, ignoring comments and whitespace"
+    )]
+    Diff(diff::DiffArgs),
+    #[command(about = "Print a file with comments removed, for use as a git textconv filter")]
+    Textconv(git::TextconvArgs),
+    #[command(about = "Configure the current git repository to diff code without comments")]
+    GitSetup(git::GitSetupArgs),
+    #[command(about = "Write a comment-free copy of a git revision to a directory or archive")]
+    Export(export::ExportArgs),
+    #[command(about = "Classify each commit in a git range as comment-only, mixed or code")]
+    Commits(commits::CommitsArgs),
+    #[command(about = "Strip comments from every source file inside a tar, tar.gz or zip archive")]
+    Archive(archive::ArchiveArgs),
 }
-fn parse_language(s: &str) -> Result<TreeSitterLanguage, String> {
+#[derive(Debug, Clone)]
+struct Comment {
+    range: Range<usize>,
+    start: Point,
+    end: Point,
+    kinds: CommentKinds,
+}
+impl Comment {
+    fn text<'a>(&self, input: &'a str) -> &'a str {
+        &input[self.range.clone()]
+    }
+}
+fn parse_language(s: &str) -> Result<TreeSitterLanguage, Error> {
     match s.to_lowercase().as_str() {
         #[cfg(feature = "bash")]
         "bash" | "sh" => Ok(TreeSitterLanguage::Bash),
@@ -138,12 +356,17 @@ fn parse_language(s: &str) -> Result<TreeSitterLanguage, String> {
         "swift" => Ok(TreeSitterLanguage::Swift),
         #[cfg(feature = "typescript")]
         "typescript" | "ts" => Ok(TreeSitterLanguage::TypeScript),
-        _ => Err(format!(
+        _ => Err(Error::UnsupportedLanguage(format!(
             "Language '{}' is not supported or not compiled in this build",
             s
-        )),
+        ))),
     }
 }
+const SOURCE_EXTENSIONS: &[&str] = &[
+    "sh", "bash", "c", "h", "cs", "cpp", "cc", "cxx", "hpp", "hxx", "c++", "css", "go", "hs",
+    "html", "htm", "java", "js", "jsx", "mjs", "cjs", "lua", "php", "py", "pyw", "rb", "rs",
+    "scala", "swift", "ts", "tsx", "mts", "cts",
+];
 fn detect_language(path: &str) -> Option<TreeSitterLanguage> {
     let path_obj = Path::new(path);
     let ext = path_obj.extension()?.to_str()?.to_lowercase();
@@ -231,15 +454,22 @@ fn get_supported_languages() -> String {
         langs.join(", ")
     }
 }
-fn remove_comments_treesitter(input: &str, language: TreeSitterLanguage) -> Result<String, String> {
-    let ts_language = language.get_language();
+fn parse_source(input: &str, language: TreeSitterLanguage) -> Result<Tree, Error> {
     let mut parser = TSParser::new();
     parser
-        .set_language(&ts_language)
-        .map_err(|e| format!("Error loading grammar: {}", e))?;
-    let tree = parser
-        .parse(input, None)
-        .ok_or_else(|| "Error parsing input".to_string())?;
+        .set_language(&language.get_language())
+        .map_err(Error::GrammarLoad)?;
+    parser.parse(input, None).ok_or(Error::Parse)
+}
+fn find_comments(input: &str, language: TreeSitterLanguage) -> Result<Vec<Comment>, Error> {
+    let tree = parse_source(input, language)?;
+    find_comments_in_tree(&tree, input, language)
+}
+fn find_comments_in_tree(
+    tree: &Tree,
+    input: &str,
+    language: TreeSitterLanguage,
+) -> Result<Vec<Comment>, Error> {
     let query_str = match language {
         #[cfg(feature = "bash")]
         TreeSitterLanguage::Bash => "(comment) @comment",
@@ -279,186 +509,748 @@ fn remove_comments_treesitter(input: &str, language: TreeSitterLanguage) -> Resu
         TreeSitterLanguage::TypeScript => "(comment) @comment",
     };
     if query_str.is_empty() {
-        return Ok(input.to_string());
+        return Ok(Vec::new());
     }
-    let query =
-        Query::new(&ts_language, query_str).map_err(|e| format!("Error creating query: {}", e))?;
+    let query = Query::new(&language.get_language(), query_str).map_err(Error::QueryCompile)?;
     let mut cursor = QueryCursor::new();
     let mut matches = cursor.matches(&query, tree.root_node(), input.as_bytes());
-    let mut comment_ranges = Vec::new();
+    let mut nodes = Vec::new();
     while let Some(m) = matches.next() {
-        for capture in m.captures {
-            comment_ranges.push(capture.node.byte_range());
-        }
+        nodes.extend(m.captures.iter().map(|capture| capture.node));
     }
-    comment_ranges.sort_by_key(|r| r.start);
-    let mut result = String::with_capacity(input.len());
+    nodes.sort_by_key(|node| node.start_byte());
+    let root = tree.root_node();
+    let mut cursor = root.walk();
+    let code_start = root
+        .named_children(&mut cursor)
+        .find(|child| {
+            child.kind() != "php_tag"
+                && nodes
+                    .binary_search_by_key(&child.start_byte(), |n| n.start_byte())
+                    .is_err()
+        })
+        .map_or(usize::MAX, |child| child.start_byte());
+    Ok(nodes
+        .into_iter()
+        .map(|node| Comment {
+            range: node.byte_range(),
+            start: node.start_position(),
+            end: node.end_position(),
+            kinds: classify::classify(input, node.byte_range(), code_start),
+        })
+        .collect())
+}
+fn remove_comments_treesitter(input: &str, language: TreeSitterLanguage) -> Result<String, Error> {
+    let comments = find_comments(input, language)?;
+    Ok(splice_comments(input, &comments))
+}
+fn splice_comments(input: &str, comments: &[Comment]) -> String {
+    splice_comments_traced(input, comments).text
+}
+fn splice_comments_traced(input: &str, comments: &[Comment]) -> Traced {
+    let mut result = Traced::with_capacity(input.len());
     let mut last_pos = 0;
-    for range in comment_ranges {
-        result.push_str(&input[last_pos..range.start]);
-        for ch in input[range.clone()].chars() {
+    for range in comments.iter().map(|c| c.range.clone()) {
+        result.push_str(&input[last_pos..range.start], last_pos);
+        for (offset, ch) in input[range.clone()].char_indices() {
             if ch == '\n' {
-                result.push('\n');
+                result.push('\n', range.start + offset);
+            }
+        }
+        last_pos = range.end;
+    }
+    result.push_str(&input[last_pos..], last_pos);
+    result
+}
+fn blank_comments(input: &str, comments: &[Comment], mode: BlankMode) -> Traced {
+    let mut result = Traced::with_capacity(input.len());
+    let mut last_pos = 0;
+    for range in comments.iter().map(|c| c.range.clone()) {
+        result.push_str(&input[last_pos..range.start], last_pos);
+        for (offset, ch) in input[range.clone()].char_indices() {
+            let origin = range.start + offset;
+            match ch {
+                '\n' | '\r' => result.push(ch, origin),
+                _ if mode == BlankMode::Bytes => {
+                    result.push_str(&" ".repeat(ch.len_utf8()), origin)
+                }
+                _ => result.push(' ', origin),
             }
         }
         last_pos = range.end;
     }
-    result.push_str(&input[last_pos..]);
-    Ok(result)
+    result.push_str(&input[last_pos..], last_pos);
+    result
+}
+fn verify_output(input: &str, output: &str, language: TreeSitterLanguage) -> Result<(), Error> {
+    let input_ok = !parse_source(input, language)?.root_node().has_error();
+    let output_ok = !parse_source(output, language)?.root_node().has_error();
+    if input_ok && !output_ok {
+        return Err(Error::Verify(
+            "output contains syntax errors that were not present in the input".to_string(),
+        ));
+    }
+    Ok(())
 }
-fn collapse_whitespace(input: &str, max_newlines: usize) -> String {
-    let lines: Vec<&str> = input.lines().collect();
-    let mut result = String::with_capacity(input.len());
+fn collapse_whitespace_traced(input: &Traced, max_newlines: usize) -> Traced {
+    let mut result = Traced::with_capacity(input.text.len());
     let mut consecutive_empty = 0;
-    for (idx, line) in lines.iter().enumerate() {
-        let is_empty = line.trim().is_empty();
+    let mut line_start = 0;
+    let mut prev_newline = None;
+    for segment in input.text.split_inclusive('\n') {
+        let content = segment.strip_suffix('\n').unwrap_or(segment);
+        let content = content.strip_suffix('\r').unwrap_or(content);
+        let is_empty = content.trim().is_empty();
         if is_empty {
             consecutive_empty += 1;
-            if consecutive_empty <= max_newlines {
-                if idx > 0 {
-                    result.push('\n');
-                }
-                result.push_str(line);
-            }
         } else {
             consecutive_empty = 0;
-            if idx > 0 {
-                result.push('\n');
+        }
+        if !is_empty || consecutive_empty <= max_newlines {
+            if let Some(newline) = prev_newline {
+                result.push('\n', newline);
             }
-            result.push_str(line);
+            result.push_traced(input, line_start..line_start + content.len());
+        }
+        if segment.ends_with('\n') {
+            prev_newline = Some(input.origins[line_start + segment.len() - 1]);
         }
+        line_start += segment.len();
     }
-    if input.ends_with('\n') && !result.ends_with('\n') {
-        result.push('\n');
+    if let Some(newline) = prev_newline
+        && input.text.ends_with('\n')
+        && !result.text.ends_with('\n')
+    {
+        result.push('\n', newline);
     }
     result
 }
+fn read_source(mut reader: impl Read) -> Result<String, Error> {
+    let mut bytes = Vec::new();
+    reader.read_to_end(&mut bytes)?;
+    String::from_utf8(bytes).map_err(|_| Error::InvalidUtf8)
+}
+fn resolve_language(
+    file_path: &str,
+    language_override: Option<TreeSitterLanguage>,
+) -> Result<TreeSitterLanguage, Error> {
+    if let Some(lang) = language_override {
+        return Ok(lang);
+    }
+    detect_language(file_path).ok_or_else(|| {
+        Error::UnsupportedLanguage("unsupported or unavailable language for this file".to_string())
+    })
+}
+struct StripOptions {
+    filter: filter::CommentFilter,
+    strip_markers: bool,
+    blank: Option<BlankMode>,
+    redact: Option<Vec<redact::RedactRule>>,
+    verify: bool,
+    collapse_whitespace: Option<usize>,
+}
+impl StripOptions {
+    fn new(args: &Args) -> Result<Self, Error> {
+        let config = config::Config::load(args.config.as_deref(), args.no_config)?;
+        Ok(StripOptions {
+            filter: filter::CommentFilter::new(&args.filter, &config)?,
+            strip_markers: args.strip_markers,
+            blank: args.blank,
+            redact: args
+                .redact
+                .then(|| redact::rules(&args.redact_pattern, &config))
+                .transpose()?,
+            verify: args.verify,
+            collapse_whitespace: args.collapse_whitespace,
+        })
+    }
+}
+fn load_filter(
+    filter_args: &filter::FilterArgs,
+    cli: &Args,
+) -> Result<filter::CommentFilter, Error> {
+    let config = config::Config::load(cli.config.as_deref(), cli.no_config)?;
+    filter::CommentFilter::new(filter_args, &config)
+}
+fn select_comments(
+    input: &str,
+    language: TreeSitterLanguage,
+    options: &StripOptions,
+    baseline: Option<&str>,
+) -> Result<Vec<Comment>, Error> {
+    let comments = markers::select(
+        find_comments(input, language)?,
+        input,
+        &options.filter,
+        options.strip_markers,
+    );
+    match baseline {
+        Some(old) => baseline::new_comments(comments, input, old, language),
+        None => Ok(comments),
+    }
+}
+struct Stripped {
+    output: Traced,
+    removed: Vec<Comment>,
+    redactions: Vec<redact::Redaction>,
+}
+fn process_source(
+    input: &str,
+    language: TreeSitterLanguage,
+    options: &StripOptions,
+    baseline: Option<&str>,
+) -> Result<Stripped, Error> {
+    let comments = select_comments(input, language, options, baseline)?;
+    let mut redactions = Vec::new();
+    let mut output = match (&options.redact, options.blank) {
+        (Some(rules), _) => {
+            let (output, found) = redact::redact_comments(input, &comments, rules);
+            redactions = found;
+            output
+        }
+        (None, Some(mode)) => blank_comments(input, &comments, mode),
+        (None, None) => splice_comments_traced(input, &comments),
+    };
+    if options.verify {
+        verify_output(input, &output.text, language)?;
+    }
+    if let Some(max_newlines) = options.collapse_whitespace {
+        output = collapse_whitespace_traced(&output, max_newlines);
+    }
+    Ok(Stripped {
+        output,
+        removed: comments,
+        redactions,
+    })
+}
+fn finish_output(
+    args: &Args,
+    file_path: Option<&str>,
+    output_file: Option<&str>,
+    language: TreeSitterLanguage,
+    input: &str,
+    stripped: Stripped,
+    saved: &mut Vec<restore::SavedFile>,
+) -> Result<String, Error> {
+    let output = stripped.output;
+    for redaction in &stripped.redactions {
+        eprintln!(
+            "{}:{}:{}: redacted {}: {}",
+            file_path.unwrap_or("<stdin>"),
+            redaction.line,
+            redaction.column,
+            redaction.rule,
+            redaction.text
+        );
+    }
+    if args.save_comments.is_some() {
+        let file = file_path.unwrap_or("<stdin>");
+        saved.push(restore::record(
+            file,
+            language,
+            input,
+            &stripped.removed,
+            &output,
+        )?);
+    }
+    let Some(map_path) = &args.source_map else {
+        return Ok(output.text);
+    };
+    let map_path = match (map_path, output_file) {
+        (Some(path), _) => path.clone(),
+        (None, Some(output_file)) => format!("{}.map", output_file),
+        (None, None) => unreachable!(),
+    };
+    let map_name = file_name(&map_path);
+    let file = output_file
+        .map(file_name)
+        .unwrap_or_else(|| map_name.strip_suffix(".map").unwrap_or(map_name));
+    let source = file_path.unwrap_or("<stdin>");
+    let map = sourcemap::render(
+        args.source_map_format,
+        file,
+        source,
+        input,
+        &output,
+        args.source_map_sources_content,
+    );
+    fs::write(&map_path, map).map_err(|e| Error::from(e).in_file(&map_path))?;
+    let mut text = output.text;
+    if args.source_map_url && matches!(language.name(), "javascript" | "typescript") {
+        if !text.is_empty() && !text.ends_with('\n') {
+            text.push('\n');
+        }
+        text.push_str(&format!("//# sourceMappingURL={}\n", map_name));
+    }
+    Ok(text)
+}
+fn file_name(path: &str) -> &str {
+    Path::new(path)
+        .file_name()
+        .and_then(|name| name.to_str())
+        .unwrap_or(path)
+}
+enum FileOutcome {
+    Processed,
+    Rewritten,
+    CommentsFound,
+    SkippedGenerated,
+}
+fn mirror_path(path: &str) -> Result<PathBuf, Error> {
+    let mut mirrored = PathBuf::new();
+    for component in Path::new(path).components() {
+        match component {
+            Component::Normal(name) => mirrored.push(name),
+            Component::ParentDir if mirrored.pop() => {}
+            Component::ParentDir => {
+                return Err(Error::Usage(
+                    "path climbs above the current directory and cannot be mirrored under --out-dir"
+                        .to_string(),
+                ));
+            }
+            Component::Prefix(_) | Component::RootDir | Component::CurDir => {}
+        }
+    }
+    Ok(mirrored)
+}
+fn sibling_path(path: &str, suffix: &str) -> PathBuf {
+    let path = Path::new(path);
+    let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+    let name = match path.extension() {
+        Some(ext) => format!("{}{}.{}", stem, suffix, ext.to_string_lossy()),
+        None => format!("{}{}", stem, suffix),
+    };
+    path.with_file_name(name)
+}
+fn output_target(args: &Args, file_path: &str) -> Result<Option<PathBuf>, Error> {
+    let target = match (&args.out_dir, &args.suffix) {
+        (Some(dir), _) => Path::new(dir).join(mirror_path(file_path)?),
+        (None, Some(suffix)) => sibling_path(file_path, suffix),
+        (None, None) => return Ok(None),
+    };
+    if fs::canonicalize(&target).ok() == Some(fs::canonicalize(file_path)?) {
+        return Err(Error::Usage(format!(
+            "output '{}' would overwrite the input",
+            target.display()
+        )));
+    }
+    Ok(Some(target))
+}
+fn write_target(target: &Path, content: &[u8], metadata: &fs::Metadata) -> Result<(), Error> {
+    let target_name = target.to_string_lossy();
+    if let Some(parent) = target.parent() {
+        fs::create_dir_all(parent).map_err(|e| Error::from(e).in_file(&target_name))?;
+    }
+    fs::write(target, content).map_err(|e| Error::from(e).in_file(&target_name))?;
+    fs::set_permissions(target, metadata.permissions())
+        .map_err(|e| Error::from(e).in_file(&target_name))?;
+    Ok(())
+}
+fn copy_other_file(file_path: &str, args: &Args) -> Result<FileOutcome, Error> {
+    let metadata = fs::metadata(file_path)?;
+    if let Some(target) = output_target(args, file_path)? {
+        write_target(&target, &fs::read(file_path)?, &metadata)?;
+    }
+    Ok(FileOutcome::Processed)
+}
+fn expand_inputs(
+    args: &Args,
+    language_override: Option<TreeSitterLanguage>,
+) -> Result<Vec<(String, bool)>, Error> {
+    let mut files = Vec::new();
+    for path in &args.files {
+        if !Path::new(path).is_dir() {
+            files.push((path.clone(), false));
+            continue;
+        }
+        for file in walk::collect_all(path)? {
+            let is_output = args.suffix.as_ref().is_some_and(|suffix| {
+                Path::new(&file)
+                    .file_stem()
+                    .is_some_and(|stem| stem.to_string_lossy().ends_with(suffix.as_str()))
+            });
+            if is_output {
+                continue;
+            }
+            if walk::is_wanted(&file, language_override) {
+                files.push((file, false));
+            } else if args.copy_other {
+                files.push((file, true));
+            }
+        }
+    }
+    Ok(files)
+}
+struct Outputs {
+    saved: Vec<restore::SavedFile>,
+    bundle: Option<bundle::Bundle>,
+    edits: Vec<serde_json::Value>,
+}
 fn process_single_file(
     file_path: &str,
     language_override: Option<TreeSitterLanguage>,
-    collapse: Option<usize>,
-) -> Result<String, String> {
-    let metadata =
-        fs::metadata(file_path).map_err(|e| format!("Cannot access '{}': {}", file_path, e))?;
+    args: &Args,
+    options: &StripOptions,
+    baseline: Option<&git::Baseline>,
+    index: Option<&mut git::BlobReader>,
+    outputs: &mut Outputs,
+) -> Result<FileOutcome, Error> {
+    let metadata = fs::metadata(file_path)?;
     if metadata.is_dir() {
-        return Err(format!("'{}' is a directory, not a file", file_path));
+        return Err(Error::Io(io::Error::new(
+            io::ErrorKind::IsADirectory,
+            "is a directory, not a file",
+        )));
     }
-    let language = if let Some(lang) = language_override {
-        lang
-    } else {
-        detect_language(file_path).ok_or_else(|| {
-            format!(
-                "'{}': unsupported or unavailable language for this file",
-                file_path
-            )
-        })?
+    let language = resolve_language(file_path, language_override);
+    if language.is_err() && args.copy_other {
+        return copy_other_file(file_path, args);
+    }
+    let language = language?;
+    let target = output_target(args, file_path)?;
+    let input_content = match index {
+        Some(index) => read_source(index.read(&git::index_spec(file_path))?.as_slice())?,
+        None => read_source(fs::File::open(file_path)?)?,
     };
-    let input_content = fs::read_to_string(file_path)
-        .map_err(|e| format!("Error reading '{}': {}", file_path, e))?;
-    let mut output_content = remove_comments_treesitter(&input_content, language)
-        .map_err(|e| format!("Error processing '{}': {}", file_path, e))?;
-    if let Some(max_newlines) = collapse {
-        output_content = collapse_whitespace(&output_content, max_newlines);
+    if !args.include_generated && generated::is_generated(&input_content) {
+        if let Some(target) = &target {
+            write_target(target, input_content.as_bytes(), &metadata)?;
+        } else if let Some(bundle) = &mut outputs.bundle {
+            bundle.write(file_path, language, &input_content)?;
+        } else if !args.in_place && !args.check && args.format == edits::OutputFormat::Text {
+            write_stdout(&input_content)?;
+        }
+        return Ok(FileOutcome::SkippedGenerated);
     }
-    Ok(output_content)
-}
-fn main() {
-    let args = Args::parse();
-    if args.files.is_empty() {
-        if args.in_place {
-            eprintln!("Error: --in-place requires at least one input file");
-            process::exit(1);
+    let baseline = baseline.map(|b| b.file(file_path)).transpose()?;
+    if args.check {
+        if select_comments(&input_content, language, options, baseline.as_deref())?.is_empty() {
+            return Ok(FileOutcome::Processed);
+        }
+        write_stdout(&format!("{}\n", file_path))?;
+        return Ok(FileOutcome::CommentsFound);
+    }
+    if args.format == edits::OutputFormat::EditsJson {
+        let comments = select_comments(&input_content, language, options, baseline.as_deref())?;
+        outputs.edits.push(edits::file_edits(
+            file_path,
+            language,
+            &input_content,
+            &comments,
+        ));
+        return Ok(FileOutcome::Processed);
+    }
+    let stripped = process_source(&input_content, language, options, baseline.as_deref())?;
+    let target_name = target.as_ref().map(|t| t.to_string_lossy().into_owned());
+    if let Some(parent) = target.as_ref().and_then(|t| t.parent()) {
+        fs::create_dir_all(parent)?;
+    }
+    let output_content = finish_output(
+        args,
+        Some(file_path),
+        target_name.as_deref().or(Some(file_path)),
+        language,
+        &input_content,
+        stripped,
+        &mut outputs.saved,
+    )?;
+    if let Some(target) = &target {
+        write_target(target, output_content.as_bytes(), &metadata)?;
+    } else if args.in_place {
+        fs::write(file_path, &output_content)?;
+        if output_content != input_content {
+            return Ok(FileOutcome::Rewritten);
         }
-        let mut buffer = String::new();
-        io::stdin().read_to_string(&mut buffer).unwrap_or_else(|e| {
-            eprintln!("Error reading stdin: {}", e);
-            process::exit(1);
+    } else if let Some(bundle) = &mut outputs.bundle {
+        bundle.write(file_path, language, &output_content)?;
+    } else {
+        write_stdout(&output_content)?;
+    }
+    Ok(FileOutcome::Processed)
+}
+fn write_stdout(text: &str) -> io::Result<()> {
+    let mut stdout = io::stdout().lock();
+    stdout.write_all(text.as_bytes())?;
+    stdout.flush()
+}
+fn stdin_language_error() -> Error {
+    Error::Usage(format!(
+        "Language must be specified for stdin input (use -l/--language). Supported languages: {}",
+        get_supported_languages()
+    ))
+}
+fn for_each_source<F>(paths: &[String], cli: &Args, mut f: F) -> Result<i32, Error>
+where
+    F: FnMut(&str, TreeSitterLanguage, &str) -> Result<(), Error>,
+{
+    let language_override = cli.language.as_deref().map(parse_language).transpose()?;
+    if paths.is_empty() {
+        let language = language_override.ok_or_else(stdin_language_error)?;
+        let input = read_source(io::stdin()).map_err(|e| e.in_file("<stdin>"))?;
+        f("<stdin>", language, &input).map_err(|e| e.in_file("<stdin>"))?;
+        return Ok(EXIT_SUCCESS);
+    }
+    let mut first_failure = None;
+    let mut failed_count = 0;
+    let mut processed_count = 0;
+    for file_path in walk::collect_files(paths, language_override)? {
+        let result = resolve_language(&file_path, language_override).and_then(|language| {
+            let input = read_source(fs::File::open(&file_path)?)?;
+            f(&file_path, language, &input)
         });
-        let language = if let Some(lang_str) = &args.language {
-            parse_language(lang_str).unwrap_or_else(|e| {
-                eprintln!("Error: {}", e);
-                eprintln!(
-                    "Supported languages in this build: {}",
-                    get_supported_languages()
-                );
-                process::exit(1);
-            })
-        } else {
-            eprintln!("Error: Language must be specified for stdin input (use -l/--language)");
-            eprintln!("Supported languages: {}", get_supported_languages());
-            process::exit(1);
+        match result {
+            Ok(()) => processed_count += 1,
+            Err(e) => {
+                let e = e.in_file(&file_path);
+                if !cli.force || e.is_broken_pipe() {
+                    return Err(e);
+                }
+                e.report(cli.error_format);
+                first_failure.get_or_insert(e.exit_code());
+                failed_count += 1;
+            }
+        }
+    }
+    if let Some(code) = first_failure {
+        eprintln!("\nProcessed: {}, Failed: {}", processed_count, failed_count);
+        return Ok(code);
+    }
+    Ok(EXIT_SUCCESS)
+}
+fn run(args: &Args) -> Result<i32, Error> {
+    if let Some(command) = &args.command {
+        return match command {
+            Command::Extract(extract_args) => extract::run(extract_args, args),
+            Command::Docs(docs_args) => docs::run(docs_args, args),
+            Command::Todos(todos_args) => todo::run(todos_args, args),
+            Command::CommentedCode(code_args) => commented_code::run(code_args, args),
+            Command::Restore(restore_args) => restore::run(restore_args, args),
+            Command::ScanSecrets(secrets_args) => secrets::run(secrets_args, args),
+            Command::Hash(hash_args) => hash::run(hash_args, args),
+            Command::Diff(diff_args) => diff::run(diff_args, args),
+            Command::Textconv(textconv_args) => git::run_textconv(textconv_args, args),
+            Command::GitSetup(setup_args) => git::run_setup(setup_args),
+            Command::Export(export_args) => export::run(export_args, args),
+            Command::Commits(commits_args) => commits::run(commits_args, args),
+            Command::Archive(archive_args) => archive::run(archive_args, args),
         };
-        let mut output_content =
-            remove_comments_treesitter(&buffer, language).unwrap_or_else(|e| {
-                eprintln!("Error: {}", e);
-                process::exit(1);
-            });
-        if let Some(max_newlines) = args.collapse_whitespace {
-            output_content = collapse_whitespace(&output_content, max_newlines);
+    }
+    let language_override = args.language.as_deref().map(parse_language).transpose()?;
+    let options = StripOptions::new(args)?;
+    let mut outputs = Outputs {
+        saved: Vec::new(),
+        bundle: args
+            .bundle
+            .map(|format| bundle::Bundle::new(format, args.bundle_header.as_deref())),
+        edits: Vec::new(),
+    };
+    let from_git = args.staged || args.changed_since.is_some();
+    let files = if from_git {
+        git::changed_files(
+            args.changed_since.as_deref(),
+            &args.files,
+            language_override,
+        )?
+        .into_iter()
+        .map(|file| (file, false))
+        .collect()
+    } else {
+        expand_inputs(args, language_override)?
+    };
+    let baseline = args
+        .only_new_since
+        .as_deref()
+        .map(git::Baseline::open)
+        .transpose()?;
+    if (from_git || !args.files.is_empty()) && files.is_empty() {
+        return Ok(EXIT_SUCCESS);
+    }
+    if files.is_empty() {
+        if args.in_place {
+            return Err(Error::Usage(
+                "--in-place requires at least one input file".to_string(),
+            ));
+        }
+        if let Some(None) = &args.source_map {
+            return Err(Error::Usage(
+                "--source-map needs a PATH when reading from stdin".to_string(),
+            ));
         }
-        print!("{}", output_content);
-        io::stdout().flush().unwrap();
-        return;
-    }
-    let language_override = if let Some(lang_str) = &args.language {
-        Some(parse_language(lang_str).unwrap_or_else(|e| {
-            eprintln!("Error: {}", e);
-            eprintln!(
-                "Supported languages in this build: {}",
-                get_supported_languages()
-            );
-            process::exit(1);
-        }))
+        if args.only_new_since.is_some() {
+            return Err(Error::Usage(
+                "--only-new-since needs input files, not stdin".to_string(),
+            ));
+        }
+        let language = language_override.ok_or_else(stdin_language_error)?;
+        let buffer = read_source(io::stdin()).map_err(|e| e.in_file("<stdin>"))?;
+        if args.check {
+            if select_comments(&buffer, language, &options, None)?.is_empty() {
+                return Ok(EXIT_SUCCESS);
+            }
+            write_stdout("<stdin>\n")?;
+            return Ok(EXIT_COMMENTS_FOUND);
+        }
+        if args.format == edits::OutputFormat::EditsJson {
+            let comments = select_comments(&buffer, language, &options, None)?;
+            edits::print(&[edits::file_edits("<stdin>", language, &buffer, &comments)])?;
+            return Ok(EXIT_SUCCESS);
+        }
+        let stripped = process_source(&buffer, language, &options, None)?;
+        let output_content = finish_output(
+            args,
+            None,
+            None,
+            language,
+            &buffer,
+            stripped,
+            &mut outputs.saved,
+        )?;
+        if let Some(path) = &args.save_comments {
+            restore::CommentStore::new(outputs.saved).write(path)?;
+        }
+        match &mut outputs.bundle {
+            Some(bundle) => {
+                bundle.write("<stdin>", language, &output_content)?;
+                if args.bundle_totals {
+                    write_stdout(&bundle.totals())?;
+                }
+            }
+            None => write_stdout(&output_content)?,
+        }
+        return Ok(EXIT_SUCCESS);
+    }
+    if let Some(Some(_)) = &args.source_map
+        && files.len() > 1
+    {
+        return Err(Error::Usage(
+            "--source-map=PATH can only be used with a single input".to_string(),
+        ));
+    }
+    let to_files = args.in_place
+        || args.out_dir.is_some()
+        || args.suffix.is_some()
+        || args.bundle.is_some()
+        || args.format == edits::OutputFormat::EditsJson;
+    if files.len() > 1 && !to_files && !args.check {
+        return Err(Error::Usage(
+            "Cannot output multiple files to stdout without --in-place, --out-dir or --suffix"
+                .to_string(),
+        ));
+    }
+    let mut first_failure = None;
+    let mut failed_count = 0;
+    let mut processed_count = 0;
+    let mut skipped_count = 0;
+    let mut comments_found = false;
+    if args.in_place && (args.staged || args.restage) {
+        let paths: Vec<String> = files.iter().map(|(file, _)| file.clone()).collect();
+        git::check_fully_staged(&paths)?;
+    }
+    let mut index = if args.staged {
+        Some(git::BlobReader::new()?)
     } else {
         None
     };
-    let mut failed_files = Vec::new();
-    let mut processed_count = 0;
-    for file_path in &args.files {
-        match process_single_file(file_path, language_override, args.collapse_whitespace) {
-            Ok(output_content) => {
-                if args.in_place {
-                    if let Err(e) = fs::write(file_path, &output_content) {
-                        eprintln!("Error writing to '{}': {}", file_path, e);
-                        failed_files.push(file_path.clone());
-                        if !args.force {
-                            process::exit(1);
-                        }
-                    } else {
-                        processed_count += 1;
-                    }
-                } else {
-                    if args.files.len() > 1 {
-                        eprintln!(
-                            "Error: Cannot output multiple files to stdout without --in-place"
-                        );
-                        process::exit(1);
-                    }
-                    print!("{}", output_content);
-                    io::stdout().flush().unwrap();
-                    processed_count += 1;
+    let mut rewritten = Vec::new();
+    for (file_path, other) in &files {
+        let result = if *other {
+            copy_other_file(file_path, args)
+        } else {
+            process_single_file(
+                file_path,
+                language_override,
+                args,
+                &options,
+                baseline.as_ref(),
+                index.as_mut(),
+                &mut outputs,
+            )
+        };
+        match result {
+            Ok(FileOutcome::SkippedGenerated) => skipped_count += 1,
+            Ok(outcome) => {
+                comments_found |= matches!(outcome, FileOutcome::CommentsFound);
+                if matches!(outcome, FileOutcome::Rewritten) {
+                    rewritten.push(file_path.clone());
                 }
+                processed_count += 1;
             }
-            Err(err_msg) => {
-                eprintln!("Error: {}", err_msg);
-                failed_files.push(file_path.clone());
-                if !args.force {
-                    process::exit(1);
+            Err(e) => {
+                let e = e.in_file(file_path);
+                if !args.force || e.is_broken_pipe() {
+                    return Err(e);
                 }
+                e.report(args.error_format);
+                first_failure.get_or_insert(e.exit_code());
+                failed_count += 1;
             }
         }
     }
-    if !failed_files.is_empty() {
+    if let Some(path) = &args.save_comments {
+        restore::CommentStore::new(outputs.saved).write(path)?;
+    }
+    if args.restage {
+        git::restage(&rewritten)?;
+    }
+    if args.format == edits::OutputFormat::EditsJson {
+        edits::print(&outputs.edits)?;
+    }
+    if let Some(bundle) = &outputs.bundle
+        && args.bundle_totals
+    {
+        write_stdout(&bundle.totals())?;
+    }
+    if skipped_count > 0 {
         eprintln!(
-            "\nProcessed: {}, Failed: {}",
-            processed_count,
-            failed_files.len()
+            "Skipped {} generated file(s) (use --include-generated to process them)",
+            skipped_count
         );
-        process::exit(1);
     }
+    if let Some(code) = first_failure {
+        eprintln!("\nProcessed: {}, Failed: {}", processed_count, failed_count);
+        return Ok(code);
+    }
+    if comments_found {
+        return Ok(EXIT_COMMENTS_FOUND);
+    }
+    Ok(EXIT_SUCCESS)
+}
+fn requested_error_format(argv: &[String]) -> ErrorFormat {
+    let mut format = ErrorFormat::Text;
+    let mut argv = argv.iter().take_while(|arg| *arg != "--");
+    while let Some(arg) = argv.next() {
+        let value = match arg.strip_prefix("--error-format") {
+            Some("") => argv.next().map(String::as_str),
+            Some(rest) => rest.strip_prefix('='),
+            None => None,
+        };
+        if let Some(value) = value.and_then(|v| ErrorFormat::from_str(v, false).ok()) {
+            format = value;
+        }
+    }
+    format
+}
+fn main() {
+    let args = Args::try_parse().unwrap_or_else(|e| {
+        if !e.use_stderr() {
+            e.exit();
+        }
+        let argv: Vec<String> = std::env::args_os()
+            .map(|arg| arg.to_string_lossy().into_owned())
+            .collect();
+        let e = Error::from(e);
+        e.report(requested_error_format(&argv));
+        process::exit(e.exit_code());
+    });
+    let code = run(&args).unwrap_or_else(|e| {
+        if e.is_broken_pipe() {
+            return EXIT_SUCCESS;
+        }
+        e.report(args.error_format);
+        e.exit_code()
+    });
+    process::exit(code);
 }
-
-
 
 #[cfg(test)]
 mod tests {
@@ -753,4 +1545,97 @@ mod tests {
         assert!(result.contains("http://example.com"));
         assert!(result.contains("// not a comment"));
     }
+    #[test]
+    fn test_unknown_language_error_kind() {
+        let err = parse_language("klingon").unwrap_err();
+        assert_eq!(err.exit_code(), error::EXIT_UNSUPPORTED_LANGUAGE);
+    }
+    #[test]
+    fn test_invalid_utf8_source() {
+        let err = read_source(&b"\xff\xfe"[..]).unwrap_err();
+        assert_eq!(err.exit_code(), error::EXIT_INVALID_UTF8);
+    }
+    #[test]
+    #[cfg(feature = "python")]
+    fn test_verify_accepts_clean_output() {
+        let input = "x = 1  # one\n";
+        let output = remove_comments_treesitter(input, TreeSitterLanguage::Python).unwrap();
+        assert!(verify_output(input, &output, TreeSitterLanguage::Python).is_ok());
+        let err = verify_output(input, "x = (", TreeSitterLanguage::Python).unwrap_err();
+        assert_eq!(err.exit_code(), error::EXIT_VERIFY);
+    }
+    #[test]
+    #[cfg(feature = "python")]
+    fn test_keep_todos_preserves_marker_comments() {
+        let args = Args::parse_from(["rmcm", "--keep-todos", "x.py"]);
+        let input = "# TODO: keep me\n# drop me\nx = 1\n";
+        let options = StripOptions::new(&args).unwrap();
+        let output = process_source(input, TreeSitterLanguage::Python, &options, None)
+            .unwrap()
+            .output
+            .text;
+        assert_eq!(output, "# TODO: keep me\n\nx = 1\n");
+    }
+    #[test]
+    #[cfg(feature = "rust-lang")]
+    fn test_blank_preserves_offsets() {
+        let input = "let x = 1; // caf\u{e9}\r\n/* a\nb */ let y = 2;\n";
+        let comments = find_comments(input, TreeSitterLanguage::Rust).unwrap();
+        let bytes = blank_comments(input, &comments, BlankMode::Bytes).text;
+        assert_eq!(bytes.len(), input.len());
+        assert_eq!(bytes, "let x = 1;         \r\n    \n     let y = 2;\n");
+        let chars = blank_comments(input, &comments, BlankMode::Chars).text;
+        assert_eq!(chars.chars().count(), input.chars().count());
+        assert_eq!(chars.len(), input.len() - 1);
+    }
+    #[test]
+    #[cfg(feature = "python")]
+    fn test_collapse_keeps_origins() {
+        let input = "# a\r\n\r\n\r\nx = 1\r\n";
+        let comments = find_comments(input, TreeSitterLanguage::Python).unwrap();
+        let output = collapse_whitespace_traced(&splice_comments_traced(input, &comments), 0);
+        assert_eq!(output.text, "\nx = 1\n");
+        assert_eq!(output.origins.len(), output.text.len());
+        assert_eq!(&input[output.origins[1]..output.origins[1] + 5], "x = 1");
+    }
+    #[test]
+    fn test_usage_errors_honor_error_format() {
+        let argv = |args: &[&str]| -> Vec<String> { args.iter().map(|a| a.to_string()).collect() };
+        assert_eq!(
+            requested_error_format(&argv(&["rmcm", "--bogus", "--error-format", "json"])),
+            ErrorFormat::Json
+        );
+        assert_eq!(
+            requested_error_format(&argv(&["rmcm", "--error-format=json", "x"])),
+            ErrorFormat::Json
+        );
+        assert_eq!(
+            requested_error_format(&argv(&["rmcm", "--", "--error-format=json"])),
+            ErrorFormat::Text
+        );
+        let e = Error::from(Args::try_parse_from(["rmcm", "--bogus", "x"]).unwrap_err());
+        assert_eq!(e.exit_code(), error::EXIT_USAGE);
+        assert!(e.to_string().starts_with("unexpected argument '--bogus'"));
+    }
+    #[test]
+    fn test_output_paths() {
+        assert_eq!(
+            mirror_path("./src/../lib/a.rs").unwrap(),
+            PathBuf::from("lib/a.rs")
+        );
+        assert_eq!(mirror_path("/abs/a.rs").unwrap(), PathBuf::from("abs/a.rs"));
+        assert!(matches!(mirror_path("../a.rs"), Err(Error::Usage(_))));
+        assert!(matches!(
+            mirror_path("src/../../a.rs"),
+            Err(Error::Usage(_))
+        ));
+        assert_eq!(
+            sibling_path("web/app.js", ".min"),
+            PathBuf::from("web/app.min.js")
+        );
+        assert_eq!(
+            sibling_path("bin/tool", ".stripped"),
+            PathBuf::from("bin/tool.stripped")
+        );
+    }
 }
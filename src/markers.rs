@@ -0,0 +1,129 @@
+use crate::Comment;
+use crate::filter::CommentFilter;
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Marker {
+    Off,
+    On,
+    Keep,
+    IgnoreFile,
+}
+pub fn parse_marker(text: &str) -> Option<Marker> {
+    let mut start = 0;
+    while let Some(pos) = text[start..].find("rmcm:") {
+        let at = start + pos;
+        start = at + "rmcm:".len();
+        if text[..at]
+            .chars()
+            .next_back()
+            .is_some_and(|c| c.is_alphanumeric() || c == '_')
+        {
+            continue;
+        }
+        let rest = &text[start..];
+        let name_len = rest
+            .find(|c: char| !(c.is_ascii_lowercase() || c == '-'))
+            .unwrap_or(rest.len());
+        let marker = match &rest[..name_len] {
+            "off" => Marker::Off,
+            "on" => Marker::On,
+            "keep" => Marker::Keep,
+            "ignore-file" => Marker::IgnoreFile,
+            _ => continue,
+        };
+        return Some(marker);
+    }
+    None
+}
+pub fn select(
+    comments: Vec<Comment>,
+    input: &str,
+    filter: &CommentFilter,
+    strip_markers: bool,
+) -> Vec<Comment> {
+    let markers: Vec<Option<Marker>> = comments
+        .iter()
+        .map(|c| parse_marker(c.text(input)))
+        .collect();
+    if markers.contains(&Some(Marker::IgnoreFile)) {
+        return Vec::new();
+    }
+    let mut off = false;
+    let mut keep_next = false;
+    let mut selected = Vec::new();
+    for (comment, marker) in comments.into_iter().zip(markers) {
+        match marker {
+            Some(marker) => {
+                match marker {
+                    Marker::Off => off = true,
+                    Marker::On => off = false,
+                    Marker::Keep => keep_next = true,
+                    Marker::IgnoreFile => {}
+                }
+                if strip_markers {
+                    selected.push(comment);
+                }
+            }
+            None => {
+                let protected = off || keep_next;
+                keep_next = false;
+                if !protected && filter.should_remove(&comment, input) {
+                    selected.push(comment);
+                }
+            }
+        }
+    }
+    selected
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn test_parse_marker() {
+        assert_eq!(parse_marker("// rmcm:off"), Some(Marker::Off));
+        assert_eq!(
+            parse_marker("# rmcm:keep -- lookup table"),
+            Some(Marker::Keep)
+        );
+        assert_eq!(
+            parse_marker("/* rmcm:ignore-file */"),
+            Some(Marker::IgnoreFile)
+        );
+        assert_eq!(parse_marker("// rmcm:offline"), None);
+        assert_eq!(parse_marker("// xrmcm:off"), None);
+        assert_eq!(parse_marker("// plain comment"), None);
+    }
+    #[test]
+    #[cfg(feature = "python")]
+    fn test_regions_and_keep() {
+        let input = "# a\n# rmcm:off\n# b\n# rmcm:on\n# c\n# rmcm:keep\n# d\n# e\nx = 1\n";
+        let comments = crate::find_comments(input, crate::TreeSitterLanguage::Python).unwrap();
+        let filter = CommentFilter::default();
+        let texts = |strip| -> Vec<String> {
+            select(comments.clone(), input, &filter, strip)
+                .iter()
+                .map(|c| c.text(input).to_string())
+                .collect()
+        };
+        assert_eq!(texts(false), ["# a", "# c", "# e"]);
+        assert_eq!(
+            texts(true),
+            [
+                "# a",
+                "# rmcm:off",
+                "# rmcm:on",
+                "# c",
+                "# rmcm:keep",
+                "# e"
+            ]
+        );
+    }
+    #[test]
+    #[cfg(feature = "python")]
+    fn test_ignore_file() {
+        let input = "# rmcm:ignore-file\n# a\nx = 1  # b\n";
+        let comments = crate::find_comments(input, crate::TreeSitterLanguage::Python).unwrap();
+        let filter = CommentFilter::default();
+        assert!(select(comments.clone(), input, &filter, false).is_empty());
+        assert!(select(comments, input, &filter, true).is_empty());
+    }
+}
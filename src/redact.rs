@@ -0,0 +1,229 @@
+use crate::Comment;
+use crate::classify::body_spans;
+use crate::config::{Config, default_replacement};
+use crate::error::Error;
+use crate::sourcemap::Traced;
+use regex::Regex;
+use std::ops::Range;
+const DOC_TAGS: &str = "abstract|access|api|arg|argument|async|augments|author|brief|callback|category|class|classdesc|code|const|constant|constructor|copyright|covers|default|defaultvalue|deprecated|desc|description|details|docroot|emits|enum|event|example|exception|exports|extends|external|file|fileoverview|fires|func|function|generator|global|hideconstructor|ignore|implements|import|inheritdoc|inner|instance|interface|internal|kind|lends|license|link|linkcode|linkplain|listens|literal|member|memberof|method|mixes|mixin|module|name|namespace|note|overload|override|package|param|post|pre|private|prop|property|protected|public|readonly|ref|remarks|requires|retval|return|returns|sa|see|serial|serialdata|serialfield|since|static|summary|template|test|this|throw|throws|todo|tutorial|type|typedef|typeparam|uses|value|var|variation|version|virtual|warning|yield|yields";
+const STANDARD_PREFIXES: &str =
+    "AES|ANSI|ASCII|CVE|CWE|ECMA|ES|HTTP|IEC|IEEE|ISO|MD|PEP|RFC|SHA|SSL|TLS|UCS|UTF|WCAG";
+const BUILTIN_RULES: &[(&str, &str, &str, Option<&str>)] = &[
+    (
+        "url",
+        r#"\b[a-zA-Z][a-zA-Z0-9+.-]*://[^\s<>"'`)\]]+"#,
+        "[URL]",
+        None,
+    ),
+    (
+        "email",
+        r"\b[A-Za-z0-9._%+-]+@[A-Za-z0-9-]+(?:\.[A-Za-z0-9-]+)*\.[A-Za-z]{2,}\b",
+        "[EMAIL]",
+        None,
+    ),
+    (
+        "hostname",
+        r"\b(?:[A-Za-z0-9-]+\.)+(?:internal|corp|local|lan|intranet|intra|private)\b",
+        "[HOST]",
+        None,
+    ),
+    ("ip", r"\b(?:\d{1,3}\.){3}\d{1,3}\b", "[IP]", None),
+    (
+        "ticket",
+        r"\b[A-Z]{2,}[A-Z0-9]*-\d+\b",
+        "[TICKET]",
+        Some(STANDARD_PREFIXES),
+    ),
+    (
+        "name",
+        r"(?:@author|[Aa]uthor:|[Ww]ritten by|[Cc]ontact:|[Mm]aintainer:|[Oo]wner:)\s+(\p{Lu}\p{Ll}+(?:[ \t]+\p{Lu}\p{Ll}+)+)",
+        "[NAME]",
+        None,
+    ),
+    (
+        "owner",
+        r"\b(?:TODO|FIXME|HACK|XXX|BUG)\(([^)]+)\)",
+        "[NAME]",
+        None,
+    ),
+    (
+        "mention",
+        r"(?:^|[\s(])@([A-Za-z0-9][A-Za-z0-9_-]*)\b",
+        "[USER]",
+        Some(DOC_TAGS),
+    ),
+];
+#[derive(Debug, Clone)]
+pub struct RedactRule {
+    pub name: String,
+    pub regex: Regex,
+    pub replacement: String,
+    pub exclude: Option<Regex>,
+}
+#[derive(Debug, Clone, PartialEq)]
+pub struct Redaction {
+    pub rule: String,
+    pub text: String,
+    pub line: usize,
+    pub column: usize,
+}
+pub fn rules(patterns: &[Regex], config: &Config) -> Result<Vec<RedactRule>, Error> {
+    let mut rules: Vec<RedactRule> = patterns
+        .iter()
+        .map(|regex| RedactRule {
+            name: "custom".to_string(),
+            regex: regex.clone(),
+            replacement: default_replacement(),
+            exclude: None,
+        })
+        .collect();
+    for rule in &config.redact {
+        rules.push(RedactRule {
+            name: rule.name.clone(),
+            regex: Regex::new(&rule.pattern).map_err(|e| Error::Config(e.to_string()))?,
+            replacement: rule.replacement.clone(),
+            exclude: None,
+        });
+    }
+    if config.redact_builtins.unwrap_or(true) {
+        rules.extend(
+            BUILTIN_RULES
+                .iter()
+                .map(|(name, pattern, replacement, exclude)| RedactRule {
+                    name: name.to_string(),
+                    regex: Regex::new(pattern).unwrap(),
+                    replacement: replacement.to_string(),
+                    exclude: exclude.map(|words| {
+                        Regex::new(&format!(r"(?i)^(?:{})(?:-\d+)?$", words)).unwrap()
+                    }),
+                }),
+        );
+    }
+    Ok(rules)
+}
+fn matches(text: &str, spans: &[Range<usize>], rules: &[RedactRule]) -> Vec<(Range<usize>, usize)> {
+    let mut found: Vec<(Range<usize>, usize)> = Vec::new();
+    for span in spans {
+        for (rule_idx, rule) in rules.iter().enumerate() {
+            for captures in rule.regex.captures_iter(&text[span.clone()]) {
+                let m = captures.get(1).or_else(|| captures.get(0)).unwrap();
+                if rule
+                    .exclude
+                    .as_ref()
+                    .is_some_and(|e| e.is_match(m.as_str()))
+                {
+                    continue;
+                }
+                let range = span.start + m.start()..span.start + m.end();
+                if range.is_empty()
+                    || found
+                        .iter()
+                        .any(|(r, _)| r.start < range.end && range.start < r.end)
+                {
+                    continue;
+                }
+                found.push((range, rule_idx));
+            }
+        }
+    }
+    found.sort_by_key(|(r, _)| r.start);
+    found
+}
+pub fn redact_comments(
+    input: &str,
+    comments: &[Comment],
+    rules: &[RedactRule],
+) -> (Traced, Vec<Redaction>) {
+    let mut result = Traced::with_capacity(input.len());
+    let mut redactions = Vec::new();
+    let mut last_pos = 0;
+    for comment in comments {
+        let text = comment.text(input);
+        let base = comment.range.start;
+        for (range, rule_idx) in matches(text, &body_spans(text), rules) {
+            let rule = &rules[rule_idx];
+            let before = &text[..range.start];
+            let line = comment.start.row + before.matches('\n').count();
+            let column = match before.rfind('\n') {
+                Some(pos) => range.start - pos - 1,
+                None => comment.start.column + range.start,
+            };
+            redactions.push(Redaction {
+                rule: rule.name.clone(),
+                text: text[range.clone()].to_string(),
+                line: line + 1,
+                column: column + 1,
+            });
+            result.push_str(&input[last_pos..base + range.start], last_pos);
+            for ch in rule.replacement.chars() {
+                result.push(ch, base + range.start);
+            }
+            last_pos = base + range.end;
+        }
+    }
+    result.push_str(&input[last_pos..], last_pos);
+    (result, redactions)
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::classify::classify;
+    fn redact(text: &str, rules: &[RedactRule]) -> (String, Vec<Redaction>) {
+        let comment = Comment {
+            range: 0..text.len(),
+            start: tree_sitter::Point::default(),
+            end: tree_sitter::Point::default(),
+            kinds: classify(text, 0..text.len(), usize::MAX),
+        };
+        let (output, redactions) = redact_comments(text, &[comment], rules);
+        (output.text, redactions)
+    }
+    #[test]
+    fn test_builtin_rules() {
+        let rules = rules(&[], &Config::default()).unwrap();
+        let (output, redactions) = redact(
+            "// TODO(jdoe): ask bob@example.com about PROJ-142, see https://wiki.acme.corp/x and db1.acme.internal",
+            &rules,
+        );
+        assert_eq!(
+            output,
+            "// TODO([NAME]): ask [EMAIL] about [TICKET], see [URL] and [HOST]"
+        );
+        let names: Vec<&str> = redactions.iter().map(|r| r.rule.as_str()).collect();
+        assert_eq!(names, ["owner", "email", "ticket", "url", "hostname"]);
+        assert_eq!(redactions[1].column, 20);
+        let (output, _) = redact("/*\n * Author: Jane Smith\n * cc @alice\n */", &rules);
+        assert_eq!(output, "/*\n * Author: [NAME]\n * cc @[USER]\n */");
+    }
+    #[test]
+    fn test_doc_tags_and_standards_are_kept() {
+        let rules = rules(&[], &Config::default()).unwrap();
+        let doc = "/**\n * Hashes with SHA-256 over UTF-8 text (RFC-2119, ISO-8601).\n * @param x the input\n * @returns {string} the digest, see {@link Foo}\n * @author Jane Smith\n */";
+        let (output, redactions) = redact(doc, &rules);
+        assert_eq!(
+            output,
+            "/**\n * Hashes with SHA-256 over UTF-8 text (RFC-2119, ISO-8601).\n * @param x the input\n * @returns {string} the digest, see {@link Foo}\n * @author [NAME]\n */"
+        );
+        assert_eq!(redactions.len(), 1);
+        let (output, _) = redact("// X-1 and A1-2 stay, OPS-7 goes", &rules);
+        assert_eq!(output, "// X-1 and A1-2 stay, [TICKET] goes");
+    }
+    #[test]
+    fn test_delimiters_are_never_redacted() {
+        let rules = rules(&[], &Config::default()).unwrap();
+        let (output, _) = redact("/* http://example.com*/", &rules);
+        assert_eq!(output, "/* [URL]*/");
+    }
+    #[test]
+    fn test_user_rules_run_first() {
+        let config = Config::parse(
+            "redact_builtins = false\n[[redact]]\nname = \"customer\"\npattern = \"ACME\\\\w*\"\nreplacement = \"[CUSTOMER]\"\n",
+        )
+        .unwrap();
+        let rules = rules(&[Regex::new("secret").unwrap()], &config).unwrap();
+        let (output, redactions) = redact("# ACMEcorp secret PROJ-1", &rules);
+        assert_eq!(output, "# [CUSTOMER] [REDACTED] PROJ-1");
+        assert_eq!(redactions[0].rule, "customer");
+        assert_eq!(redactions[1].rule, "custom");
+    }
+}
@@ -0,0 +1,452 @@
+use crate::error::{EXIT_SUCCESS, EXIT_UNPLACED_COMMENTS, Error};
+use crate::sourcemap::Traced;
+use crate::{
+    Args, Comment, TreeSitterLanguage, find_comments_in_tree, parse_language, parse_source,
+    read_source, resolve_language,
+};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::io::{self, Write};
+use std::ops::Range;
+const ANCHOR_TOKENS: usize = 3;
+const STORE_VERSION: u32 = 1;
+#[derive(clap::Args, Debug)]
+pub struct RestoreArgs {
+    #[arg(value_name = "STORE")]
+    pub store: String,
+    #[arg(value_name = "FILES")]
+    pub files: Vec<String>,
+    #[arg(short, long)]
+    pub in_place: bool,
+    #[arg(long, value_name = "PATH")]
+    pub target: Option<String>,
+}
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CommentStore {
+    pub version: u32,
+    pub files: Vec<SavedFile>,
+}
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SavedFile {
+    pub file: String,
+    pub language: String,
+    pub gaps: Vec<Gap>,
+}
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Gap {
+    pub token_index: usize,
+    pub before: Vec<String>,
+    pub after: Vec<String>,
+    pub original: String,
+    pub stripped: String,
+    pub comments: Vec<SavedComment>,
+}
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SavedComment {
+    pub text: String,
+    pub offset: usize,
+    pub line: usize,
+    pub column: usize,
+}
+struct Token<'a> {
+    range: Range<usize>,
+    text: &'a str,
+}
+impl CommentStore {
+    pub fn new(files: Vec<SavedFile>) -> Self {
+        CommentStore {
+            version: STORE_VERSION,
+            files,
+        }
+    }
+    pub fn write(&self, path: &str) -> Result<(), Error> {
+        let json = serde_json::to_string_pretty(self).map_err(io::Error::other)?;
+        fs::write(path, json + "\n").map_err(|e| Error::from(e).in_file(path))
+    }
+    pub fn read(path: &str) -> Result<Self, Error> {
+        let text = fs::read_to_string(path).map_err(|e| Error::from(e).in_file(path))?;
+        let store: CommentStore = serde_json::from_str(&text)
+            .map_err(|e| Error::Config(format!("invalid comment store: {}", e)).in_file(path))?;
+        if store.version != STORE_VERSION {
+            return Err(Error::Config(format!(
+                "unsupported comment store version {}",
+                store.version
+            ))
+            .in_file(path));
+        }
+        Ok(store)
+    }
+}
+fn tokens(input: &str, language: TreeSitterLanguage) -> Result<Vec<Token<'_>>, Error> {
+    let tree = parse_source(input, language)?;
+    let mut comments = find_comments_in_tree(&tree, input, language)?;
+    comments.sort_by_key(|c| c.range.start);
+    let mut comments = comments.iter().peekable();
+    let mut tokens = Vec::new();
+    let mut cursor = tree.walk();
+    let mut descend = true;
+    loop {
+        let node = cursor.node();
+        let range = node.byte_range();
+        let mut in_comment = false;
+        if descend {
+            while comments.next_if(|c| c.range.end <= range.start).is_some() {}
+            in_comment = comments
+                .peek()
+                .is_some_and(|c| c.range.start <= range.start && range.end <= c.range.end);
+        }
+        if descend && !in_comment && cursor.goto_first_child() {
+            continue;
+        }
+        if descend && !in_comment && node.child_count() == 0 {
+            let text = input[range.clone()].trim();
+            if !text.is_empty() {
+                tokens.push(Token { range, text });
+            }
+        }
+        if cursor.goto_next_sibling() {
+            descend = true;
+        } else if cursor.goto_parent() {
+            descend = false;
+        } else {
+            break;
+        }
+    }
+    Ok(tokens)
+}
+pub fn record(
+    file: &str,
+    language: TreeSitterLanguage,
+    input: &str,
+    removed: &[Comment],
+    output: &Traced,
+) -> Result<SavedFile, Error> {
+    let tokens = tokens(input, language)?;
+    let mut gaps: Vec<Gap> = Vec::new();
+    let output_position = |offset: usize| output.origins.partition_point(|&o| o < offset);
+    for comment in removed {
+        let index = tokens.partition_point(|t| t.range.start < comment.range.start);
+        if gaps.last().is_none_or(|g| g.token_index != index) {
+            let prev_end = index.checked_sub(1).map_or(0, |i| tokens[i].range.end);
+            let next_start = tokens.get(index).map_or(input.len(), |t| t.range.start);
+            gaps.push(Gap {
+                token_index: index,
+                before: tokens[index.saturating_sub(ANCHOR_TOKENS)..index]
+                    .iter()
+                    .map(|t| t.text.to_string())
+                    .collect(),
+                after: tokens[index..(index + ANCHOR_TOKENS).min(tokens.len())]
+                    .iter()
+                    .map(|t| t.text.to_string())
+                    .collect(),
+                original: input[prev_end..next_start].to_string(),
+                stripped: output.text[output_position(prev_end)..output_position(next_start)]
+                    .to_string(),
+                comments: Vec::new(),
+            });
+        }
+        let gap = gaps.last_mut().unwrap();
+        let prev_end = index.checked_sub(1).map_or(0, |i| tokens[i].range.end);
+        gap.comments.push(SavedComment {
+            text: comment.text(input).to_string(),
+            offset: comment.range.start - prev_end,
+            line: comment.start.row + 1,
+            column: comment.start.column + 1,
+        });
+    }
+    Ok(SavedFile {
+        file: file.to_string(),
+        language: language.name().to_string(),
+        gaps,
+    })
+}
+fn check_gap(gap: &Gap) -> Result<(), Error> {
+    for comment in &gap.comments {
+        let end = comment.offset.checked_add(comment.text.len());
+        let valid = end.is_some_and(|end| {
+            gap.original.get(comment.offset..end) == Some(comment.text.as_str())
+        });
+        if !valid {
+            return Err(Error::Config(format!(
+                "corrupt comment store: comment at {}:{} does not match its saved gap",
+                comment.line, comment.column
+            )));
+        }
+    }
+    Ok(())
+}
+fn locate(gap: &Gap, tokens: &[Token], index: &HashMap<&str, Vec<usize>>) -> Option<usize> {
+    let mut candidates: Vec<usize> = Vec::new();
+    if let Some(first) = gap.after.first() {
+        candidates.extend(index.get(first.as_str()).into_iter().flatten());
+    } else {
+        candidates.push(tokens.len());
+    }
+    if let Some(last) = gap.before.last() {
+        candidates.extend(
+            index
+                .get(last.as_str())
+                .into_iter()
+                .flatten()
+                .map(|i| i + 1),
+        );
+    } else {
+        candidates.push(0);
+    }
+    let full = gap.before.len() + gap.after.len();
+    candidates
+        .into_iter()
+        .map(|i| {
+            let before = gap
+                .before
+                .iter()
+                .rev()
+                .zip(tokens[..i].iter().rev())
+                .take_while(|(a, b)| a.as_str() == b.text)
+                .count();
+            let after = gap
+                .after
+                .iter()
+                .zip(&tokens[i..])
+                .take_while(|(a, b)| a.as_str() == b.text)
+                .count();
+            (before + after, i)
+        })
+        .filter(|&(score, _)| full == 0 || score * 2 > full)
+        .max_by_key(|&(score, i)| (score, std::cmp::Reverse(i.abs_diff(gap.token_index))))
+        .map(|(_, i)| i)
+}
+pub fn restore_source<'a>(
+    text: &str,
+    language: TreeSitterLanguage,
+    saved: &'a SavedFile,
+) -> Result<(String, Vec<&'a SavedComment>), Error> {
+    for gap in &saved.gaps {
+        check_gap(gap)?;
+    }
+    let tokens = tokens(text, language)?;
+    let mut index: HashMap<&str, Vec<usize>> = HashMap::new();
+    for (i, token) in tokens.iter().enumerate() {
+        index.entry(token.text).or_default().push(i);
+    }
+    let mut edits: Vec<(Range<usize>, String, &Gap)> = Vec::new();
+    let mut unplaced = Vec::new();
+    for gap in &saved.gaps {
+        let Some(i) = locate(gap, &tokens, &index) else {
+            unplaced.extend(&gap.comments);
+            continue;
+        };
+        let prev_end = i.checked_sub(1).map_or(0, |i| tokens[i].range.end);
+        let next_start = tokens.get(i).map_or(text.len(), |t| t.range.start);
+        let current = &text[prev_end..next_start];
+        if current == gap.original {
+            continue;
+        }
+        if current == gap.stripped {
+            edits.push((prev_end..next_start, gap.original.clone(), gap));
+            continue;
+        }
+        for comment in &gap.comments {
+            if current.contains(comment.text.trim_end_matches(['\r', '\n'])) {
+                continue;
+            }
+            let before = &gap.original[..comment.offset];
+            let after = &gap.original[comment.offset + comment.text.len()..];
+            let ends_line = comment.text.ends_with('\n')
+                || after
+                    .trim_start_matches([' ', '\t', '\r'])
+                    .starts_with('\n');
+            let body = comment.text.trim_end_matches(['\r', '\n']);
+            if i > 0 && !before.contains('\n') {
+                let space = &before[before.trim_end().len()..];
+                let space = if space.is_empty() && i < tokens.len() {
+                    " "
+                } else {
+                    space
+                };
+                edits.push((prev_end..prev_end, format!("{}{}", space, body), gap));
+            } else if i < tokens.len() && !ends_line {
+                edits.push((next_start..next_start, format!("{} ", body), gap));
+            } else if i < tokens.len() {
+                let line_start = text[..next_start].rfind('\n').map_or(0, |p| p + 1);
+                let line = &text[line_start..];
+                let indent = &line[..line.len() - line.trim_start_matches([' ', '\t']).len()];
+                edits.push((line_start..line_start, format!("{}{}\n", indent, body), gap));
+            } else {
+                let needs_newline = !text.is_empty()
+                    && !text.ends_with('\n')
+                    && !edits.iter().any(|(r, _, _)| r.start == text.len());
+                edits.push((
+                    text.len()..text.len(),
+                    format!("{}{}\n", if needs_newline { "\n" } else { "" }, body),
+                    gap,
+                ));
+            }
+        }
+    }
+    edits.sort_by_key(|(range, _, _)| range.start);
+    let mut result = String::with_capacity(text.len());
+    let mut last = 0;
+    for (range, replacement, gap) in edits {
+        if range.start < last {
+            unplaced.extend(&gap.comments);
+            continue;
+        }
+        result.push_str(&text[last..range.start]);
+        result.push_str(&replacement);
+        last = range.end;
+    }
+    result.push_str(&text[last..]);
+    Ok((result, unplaced))
+}
+pub fn run(args: &RestoreArgs, cli: &Args) -> Result<i32, Error> {
+    let stdout = io::stdout();
+    let mut out = stdout.lock();
+    let store = CommentStore::read(&args.store)?;
+    let files: Vec<&SavedFile> = if args.files.is_empty() {
+        store.files.iter().collect()
+    } else {
+        args.files
+            .iter()
+            .map(|path| {
+                store.files.iter().find(|f| &f.file == path).ok_or_else(|| {
+                    Error::Usage(format!("'{}' has no saved comments in the store", path))
+                })
+            })
+            .collect::<Result<_, _>>()?
+    };
+    if args.target.is_some() && files.len() != 1 {
+        return Err(Error::Usage(
+            "--target needs exactly one file from the store; name it after STORE".to_string(),
+        ));
+    }
+    if files.len() > 1 && !args.in_place {
+        return Err(Error::Usage(
+            "Cannot output multiple files to stdout without --in-place".to_string(),
+        ));
+    }
+    let language_override = cli.language.as_deref().map(parse_language).transpose()?;
+    let mut code = EXIT_SUCCESS;
+    for saved in files {
+        let path = args.target.as_deref().unwrap_or(&saved.file);
+        let result = (|| -> Result<Vec<&SavedComment>, Error> {
+            let language = resolve_language(path, language_override)
+                .or_else(|_| parse_language(&saved.language))?;
+            let text = read_source(fs::File::open(path)?)?;
+            let (output, unplaced) = restore_source(&text, language, saved)?;
+            if args.in_place {
+                if output != text {
+                    fs::write(path, &output)?;
+                }
+            } else {
+                write!(out, "{}", output)?;
+                out.flush()?;
+            }
+            Ok(unplaced)
+        })();
+        match result {
+            Ok(unplaced) => {
+                for comment in &unplaced {
+                    eprintln!(
+                        "{}:{}:{}: could not restore comment: {}",
+                        path,
+                        comment.line,
+                        comment.column,
+                        comment.text.lines().next().unwrap_or("")
+                    );
+                }
+                if !unplaced.is_empty() && code == EXIT_SUCCESS {
+                    code = EXIT_UNPLACED_COMMENTS;
+                }
+            }
+            Err(e) => {
+                let e = e.in_file(path);
+                if !cli.force || e.is_broken_pipe() {
+                    return Err(e);
+                }
+                e.report(cli.error_format);
+                if code == EXIT_SUCCESS || code == EXIT_UNPLACED_COMMENTS {
+                    code = e.exit_code();
+                }
+            }
+        }
+    }
+    Ok(code)
+}
+#[cfg(all(test, any(feature = "python", feature = "javascript")))]
+mod tests {
+    use super::*;
+    use crate::{find_comments, splice_comments_traced};
+    fn save(input: &str, language: TreeSitterLanguage) -> (String, SavedFile) {
+        let comments = find_comments(input, language).unwrap();
+        let output = splice_comments_traced(input, &comments);
+        let saved = record("x", language, input, &comments, &output).unwrap();
+        (output.text, saved)
+    }
+    #[test]
+    #[cfg(feature = "python")]
+    fn test_round_trip_is_exact() {
+        let input =
+            "# header\nimport os  # os\n\n\ndef f(a):\n    # body\n    return a  # done\n# tail\n";
+        let (stripped, saved) = save(input, TreeSitterLanguage::Python);
+        let (restored, unplaced) =
+            restore_source(&stripped, TreeSitterLanguage::Python, &saved).unwrap();
+        assert_eq!(restored, input);
+        assert!(unplaced.is_empty());
+    }
+    #[test]
+    #[cfg(feature = "javascript")]
+    fn test_restore_into_reformatted_code() {
+        let input = "// add\nfunction add(a, b) {\n  return a + b; // sum\n}\n";
+        let (_, saved) = save(input, TreeSitterLanguage::JavaScript);
+        let formatted = "function add(a, b) {\n    return a + b;\n}\n";
+        let (restored, unplaced) =
+            restore_source(formatted, TreeSitterLanguage::JavaScript, &saved).unwrap();
+        assert_eq!(
+            restored,
+            "// add\nfunction add(a, b) {\n    return a + b; // sum\n}\n"
+        );
+        assert!(unplaced.is_empty());
+    }
+    #[test]
+    #[cfg(feature = "python")]
+    fn test_unplaceable_comments_are_reported() {
+        let input = "x = 1\n# about y\ny = 2\n";
+        let (_, saved) = save(input, TreeSitterLanguage::Python);
+        let (restored, unplaced) =
+            restore_source("z = 3\n", TreeSitterLanguage::Python, &saved).unwrap();
+        assert_eq!(restored, "z = 3\n");
+        assert_eq!(unplaced.len(), 1);
+        assert_eq!(unplaced[0].line, 2);
+    }
+    #[test]
+    #[cfg(feature = "python")]
+    fn test_corrupt_store_is_an_error() {
+        let input = "x = 1  # \u{e9}t\u{e9}\ny = 2\n";
+        let (stripped, mut saved) = save(input, TreeSitterLanguage::Python);
+        saved.gaps[0].comments[0].offset = 3;
+        let result = restore_source(&stripped, TreeSitterLanguage::Python, &saved);
+        assert!(matches!(result, Err(Error::Config(_))));
+        saved.gaps[0].comments[0].offset = 1000;
+        let result = restore_source(&stripped, TreeSitterLanguage::Python, &saved);
+        assert!(matches!(result, Err(Error::Config(_))));
+        saved.gaps[0].original.truncate(4);
+        saved.gaps[0].comments[0].offset = 2;
+        let result = restore_source(&stripped, TreeSitterLanguage::Python, &saved);
+        assert!(matches!(result, Err(Error::Config(_))));
+    }
+    #[test]
+    #[cfg(feature = "javascript")]
+    fn test_restore_skips_comments_already_present() {
+        let input = "// head\nconst a = 1; // x\n";
+        let (_, saved) = save(input, TreeSitterLanguage::JavaScript);
+        let (restored, unplaced) =
+            restore_source(input, TreeSitterLanguage::JavaScript, &saved).unwrap();
+        assert_eq!(restored, input);
+        assert!(unplaced.is_empty());
+        let edited = "// head\nconst a = 2; // x\n";
+        let (restored, _) = restore_source(edited, TreeSitterLanguage::JavaScript, &saved).unwrap();
+        assert_eq!(restored, edited);
+    }
+}
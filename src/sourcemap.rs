@@ -0,0 +1,179 @@
+use std::ops::Range;
+const BASE64: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+#[derive(Debug, Clone, Copy, PartialEq, clap::ValueEnum)]
+pub enum SourceMapFormat {
+    V3,
+    Lines,
+}
+#[derive(Debug, Clone, Default)]
+pub struct Traced {
+    pub text: String,
+    pub origins: Vec<usize>,
+}
+impl Traced {
+    pub fn with_capacity(capacity: usize) -> Self {
+        Traced {
+            text: String::with_capacity(capacity),
+            origins: Vec::with_capacity(capacity),
+        }
+    }
+    pub fn push_str(&mut self, s: &str, origin: usize) {
+        self.text.push_str(s);
+        self.origins.extend(origin..origin + s.len());
+    }
+    pub fn push(&mut self, ch: char, origin: usize) {
+        self.text.push(ch);
+        self.origins
+            .extend(std::iter::repeat_n(origin, ch.len_utf8()));
+    }
+    pub fn push_traced(&mut self, other: &Traced, range: Range<usize>) {
+        self.text.push_str(&other.text[range.clone()]);
+        self.origins.extend_from_slice(&other.origins[range]);
+    }
+}
+pub struct LineIndex<'a> {
+    input: &'a str,
+    starts: Vec<usize>,
+}
+impl<'a> LineIndex<'a> {
+    pub fn new(input: &'a str) -> Self {
+        let starts = std::iter::once(0)
+            .chain(input.match_indices('\n').map(|(i, _)| i + 1))
+            .collect();
+        LineIndex { input, starts }
+    }
+    pub fn position(&self, offset: usize) -> (usize, usize) {
+        let line = self.starts.partition_point(|&s| s <= offset) - 1;
+        let column = utf16_len(&self.input[self.starts[line]..offset]);
+        (line, column)
+    }
+}
+fn utf16_len(s: &str) -> usize {
+    s.chars().map(char::len_utf16).sum()
+}
+fn encode_vlq(out: &mut String, value: i64) {
+    let mut vlq = if value < 0 {
+        ((-value) << 1) | 1
+    } else {
+        value << 1
+    };
+    loop {
+        let mut digit = (vlq & 31) as usize;
+        vlq >>= 5;
+        if vlq > 0 {
+            digit |= 32;
+        }
+        out.push(BASE64[digit] as char);
+        if vlq == 0 {
+            break;
+        }
+    }
+}
+pub fn mappings(input: &str, output: &Traced) -> String {
+    let index = LineIndex::new(input);
+    let mut mappings = String::new();
+    let (mut prev_line, mut prev_column) = (0i64, 0i64);
+    let mut line_start = 0;
+    for (line_idx, line) in output.text.split('\n').enumerate() {
+        if line_idx > 0 {
+            mappings.push(';');
+        }
+        let mut prev_generated = 0i64;
+        let mut expected = None;
+        let mut first = true;
+        for (offset, ch) in line.char_indices() {
+            let origin = output.origins[line_start + offset];
+            if expected != Some(origin) {
+                let (orig_line, orig_column) = index.position(origin);
+                let generated = utf16_len(&line[..offset]) as i64;
+                if !first {
+                    mappings.push(',');
+                }
+                encode_vlq(&mut mappings, generated - prev_generated);
+                encode_vlq(&mut mappings, 0);
+                encode_vlq(&mut mappings, orig_line as i64 - prev_line);
+                encode_vlq(&mut mappings, orig_column as i64 - prev_column);
+                (prev_generated, prev_line, prev_column) =
+                    (generated, orig_line as i64, orig_column as i64);
+                first = false;
+            }
+            expected = Some(origin + ch.len_utf8());
+        }
+        line_start += line.len() + 1;
+    }
+    mappings
+}
+pub fn line_map(input: &str, output: &Traced) -> Vec<usize> {
+    let index = LineIndex::new(input);
+    let mut lines = Vec::new();
+    let mut line_start = 0;
+    for line in output.text.split_inclusive('\n') {
+        lines.push(index.position(output.origins[line_start]).0 + 1);
+        line_start += line.len();
+    }
+    lines
+}
+pub fn render(
+    format: SourceMapFormat,
+    file: &str,
+    source: &str,
+    input: &str,
+    output: &Traced,
+    sources_content: bool,
+) -> String {
+    let value = match format {
+        SourceMapFormat::V3 => {
+            let mut map = serde_json::json!({
+                "version": 3,
+                "file": file,
+                "sources": [source],
+                "names": [],
+                "mappings": mappings(input, output),
+            });
+            if sources_content {
+                map["sourcesContent"] = serde_json::json!([input]);
+            }
+            map
+        }
+        SourceMapFormat::Lines => serde_json::json!({
+            "file": file,
+            "source": source,
+            "lines": line_map(input, output),
+        }),
+    };
+    value.to_string()
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn test_vlq() {
+        let mut out = String::new();
+        for value in [0, 1, -1, 15, 16, -17, 1000] {
+            encode_vlq(&mut out, value);
+            out.push(' ');
+        }
+        assert_eq!(out, "A C D e gB jB w+B ");
+    }
+    #[test]
+    fn test_sources_content_is_opt_in() {
+        let input = "a // secret\n";
+        let mut output = Traced::default();
+        output.push_str("a \n", 0);
+        let map = render(SourceMapFormat::V3, "a.js", "a.js", input, &output, false);
+        assert!(!map.contains("sourcesContent") && !map.contains("secret"));
+        let map = render(SourceMapFormat::V3, "a.js", "a.js", input, &output, true);
+        assert!(map.contains("\"sourcesContent\":[\"a // secret\\n\"]"));
+    }
+    #[test]
+    fn test_mappings_follow_removed_lines() {
+        let input = "// head\nlet a = 1; // x\nlet b = 2;\n";
+        let mut output = Traced::default();
+        output.push('\n', 7);
+        output.push_str("let a = 1; ", 8);
+        output.push('\n', 23);
+        output.push_str("let b = 2;\n", 24);
+        assert_eq!(mappings(input, &output), ";AACA;AACA;");
+        assert_eq!(line_map(input, &output), [1, 2, 3]);
+    }
+}
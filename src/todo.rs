@@ -0,0 +1,225 @@
+use crate::error::Error;
+use crate::{Args, find_comments, for_each_source};
+use std::io::{self, Write};
+pub const TAGS: &[&str] = &["TODO", "FIXME", "HACK", "XXX", "BUG"];
+#[derive(Debug, Clone, Copy, PartialEq, clap::ValueEnum)]
+pub enum TodoFormat {
+    Text,
+    Json,
+}
+#[derive(clap::Args, Debug)]
+pub struct TodosArgs {
+    #[arg(value_name = "PATHS")]
+    pub paths: Vec<String>,
+    #[arg(long, value_enum, value_name = "FORMAT", default_value = "text")]
+    pub format: TodoFormat,
+    #[arg(long = "tag", value_name = "TAG", value_delimiter = ',')]
+    pub tags: Vec<String>,
+}
+#[derive(Debug, Clone, PartialEq)]
+pub struct Marker {
+    pub tag: String,
+    pub owner: Option<String>,
+    pub ticket: Option<String>,
+    pub message: String,
+    pub line: usize,
+    pub column: usize,
+}
+struct Item {
+    file: String,
+    marker: Marker,
+}
+pub fn run(args: &TodosArgs, cli: &Args) -> Result<i32, Error> {
+    let stdout = io::stdout();
+    let mut out = stdout.lock();
+    let tags: Vec<String> = if args.tags.is_empty() {
+        TAGS.iter().map(|t| t.to_string()).collect()
+    } else {
+        args.tags.iter().map(|t| t.to_uppercase()).collect()
+    };
+    let mut items = Vec::new();
+    let code = for_each_source(&args.paths, cli, |path, language, input| {
+        for comment in find_comments(input, language)? {
+            for mut marker in find_markers(comment.text(input), &tags) {
+                if marker.line == 0 {
+                    marker.column += comment.start.column;
+                }
+                marker.line += comment.start.row + 1;
+                marker.column += 1;
+                items.push(Item {
+                    file: path.to_string(),
+                    marker,
+                });
+            }
+        }
+        Ok(())
+    })?;
+    let groups: Vec<(&String, Vec<&Item>)> = tags
+        .iter()
+        .filter_map(|tag| {
+            let group: Vec<&Item> = items.iter().filter(|i| &i.marker.tag == tag).collect();
+            (!group.is_empty()).then_some((tag, group))
+        })
+        .collect();
+    match args.format {
+        TodoFormat::Text => {
+            for (tag, group) in &groups {
+                writeln!(out, "{} ({})", tag, group.len())?;
+                for item in group {
+                    let m = &item.marker;
+                    let mut annotations = String::new();
+                    if let Some(owner) = &m.owner {
+                        annotations.push_str(&format!("({}) ", owner));
+                    }
+                    if let Some(ticket) = &m.ticket {
+                        annotations.push_str(&format!("[{}] ", ticket));
+                    }
+                    writeln!(
+                        out,
+                        "  {}:{}: {}{}",
+                        item.file, m.line, annotations, m.message
+                    )?;
+                }
+            }
+        }
+        TodoFormat::Json => {
+            let value: Vec<serde_json::Value> = groups
+                .iter()
+                .map(|(tag, group)| {
+                    let entries: Vec<serde_json::Value> = group
+                        .iter()
+                        .map(|item| {
+                            serde_json::json!({
+                                "file": item.file,
+                                "line": item.marker.line,
+                                "column": item.marker.column,
+                                "owner": item.marker.owner,
+                                "ticket": item.marker.ticket,
+                                "message": item.marker.message,
+                            })
+                        })
+                        .collect();
+                    serde_json::json!({ "tag": tag, "count": entries.len(), "items": entries })
+                })
+                .collect();
+            let output = serde_json::to_string_pretty(&value).map_err(io::Error::other)?;
+            writeln!(out, "{}", output)?;
+        }
+    }
+    Ok(code)
+}
+pub fn has_marker(text: &str) -> bool {
+    let tags: Vec<String> = TAGS.iter().map(|t| t.to_string()).collect();
+    !find_markers(text, &tags).is_empty()
+}
+pub fn find_markers(text: &str, tags: &[String]) -> Vec<Marker> {
+    let mut markers = Vec::new();
+    for (line_idx, line) in text.lines().enumerate() {
+        if let Some(marker) = parse_line(line, tags) {
+            markers.push(Marker {
+                line: line_idx,
+                ..marker
+            });
+        }
+    }
+    markers
+}
+fn parse_line(line: &str, tags: &[String]) -> Option<Marker> {
+    let (column, tag) = tags
+        .iter()
+        .filter_map(|tag| find_word(line, tag).map(|pos| (pos, tag)))
+        .min_by_key(|(pos, _)| *pos)?;
+    let mut rest = &line[column + tag.len()..];
+    let mut owner = None;
+    let mut ticket = None;
+    loop {
+        if let Some(inner) = rest.strip_prefix('(')
+            && let Some(end) = inner.find(')')
+        {
+            owner = Some(inner[..end].trim().to_string());
+            rest = &inner[end + 1..];
+        } else if let Some(inner) = rest.strip_prefix('[')
+            && let Some(end) = inner.find(']')
+        {
+            ticket = Some(inner[..end].trim().to_string());
+            rest = &inner[end + 1..];
+        } else {
+            break;
+        }
+    }
+    let rest = rest.trim_start();
+    let rest = rest
+        .strip_prefix(':')
+        .or_else(|| rest.strip_prefix('-'))
+        .unwrap_or(rest);
+    let message = rest
+        .trim()
+        .trim_end_matches("*/")
+        .trim_end_matches("-->")
+        .trim_end_matches("-}")
+        .trim_end();
+    Some(Marker {
+        tag: tag.clone(),
+        owner,
+        ticket,
+        message: message.to_string(),
+        line: 0,
+        column,
+    })
+}
+fn find_word(line: &str, word: &str) -> Option<usize> {
+    let bytes = line.as_bytes();
+    let mut start = 0;
+    while let Some(pos) = line[start..].find(word) {
+        let at = start + pos;
+        let end = at + word.len();
+        let before_ok = at == 0 || !is_word_byte(bytes[at - 1]);
+        let after_ok = end == bytes.len() || !is_word_byte(bytes[end]);
+        if before_ok && after_ok {
+            return Some(at);
+        }
+        start = end;
+    }
+    None
+}
+fn is_word_byte(b: u8) -> bool {
+    b.is_ascii_alphanumeric() || b == b'_'
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    fn tags() -> Vec<String> {
+        TAGS.iter().map(|t| t.to_string()).collect()
+    }
+    #[test]
+    fn test_owner_and_ticket_syntax() {
+        let markers = find_markers("// TODO(alice): tidy up\n// FIXME[JIRA-123] crash", &tags());
+        assert_eq!(markers.len(), 2);
+        assert_eq!(markers[0].tag, "TODO");
+        assert_eq!(markers[0].owner.as_deref(), Some("alice"));
+        assert_eq!(markers[0].message, "tidy up");
+        assert_eq!(markers[0].column, 3);
+        assert_eq!(markers[1].line, 1);
+        assert_eq!(markers[1].ticket.as_deref(), Some("JIRA-123"));
+        assert_eq!(markers[1].message, "crash");
+    }
+    #[test]
+    fn test_requires_whole_uppercase_word() {
+        assert!(find_markers("// DEBUG output, todo later", &tags()).is_empty());
+        assert!(find_markers("// TODOS", &tags()).is_empty());
+        let markers = find_markers("/* XXX - odd */", &tags());
+        assert_eq!(markers[0].message, "odd");
+    }
+    #[test]
+    #[cfg(feature = "python")]
+    fn test_markers_in_strings_are_ignored() {
+        let input = "s = 'TODO: not me'\n# TODO: me\n";
+        let comments = find_comments(input, crate::TreeSitterLanguage::Python).unwrap();
+        let found: Vec<Marker> = comments
+            .iter()
+            .flat_map(|c| find_markers(c.text(input), &tags()))
+            .collect();
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].message, "me");
+    }
+}
@@ -0,0 +1,90 @@
+use crate::error::Error;
+use crate::{TreeSitterLanguage, detect_language};
+use std::fs;
+use std::path::Path;
+pub fn collect_files(
+    paths: &[String],
+    language_override: Option<TreeSitterLanguage>,
+) -> Result<Vec<String>, Error> {
+    let mut files = Vec::new();
+    for path in paths {
+        if fs::metadata(path)
+            .map_err(|e| Error::from(e).in_file(path))?
+            .is_dir()
+        {
+            walk_dir(
+                Path::new(path),
+                &|file| is_wanted(file, language_override),
+                &mut files,
+            )?;
+        } else {
+            files.push(path.clone());
+        }
+    }
+    Ok(files)
+}
+pub fn collect_all(dir: &str) -> Result<Vec<String>, Error> {
+    let mut files = Vec::new();
+    walk_dir(Path::new(dir), &|_| true, &mut files)?;
+    Ok(files)
+}
+pub fn is_wanted(path: &str, language_override: Option<TreeSitterLanguage>) -> bool {
+    let detected = detect_language(path);
+    match language_override {
+        Some(lang) => detected == Some(lang),
+        None => detected.is_some(),
+    }
+}
+fn walk_dir(
+    dir: &Path,
+    wanted: &dyn Fn(&str) -> bool,
+    files: &mut Vec<String>,
+) -> Result<(), Error> {
+    let dir_name = dir.to_string_lossy();
+    let mut entries = fs::read_dir(dir)
+        .and_then(|rd| rd.collect::<Result<Vec<_>, _>>())
+        .map_err(|e| Error::from(e).in_file(&dir_name))?;
+    entries.sort_by_key(|entry| entry.file_name());
+    for entry in entries {
+        if entry.file_name().to_string_lossy().starts_with('.') {
+            continue;
+        }
+        let path = entry.path();
+        let file_type = entry
+            .file_type()
+            .map_err(|e| Error::from(e).in_file(&path.to_string_lossy()))?;
+        if file_type.is_dir() {
+            walk_dir(&path, wanted, files)?;
+            continue;
+        }
+        if file_type.is_symlink() && path.is_dir() {
+            continue;
+        }
+        let path_str = path.to_string_lossy().into_owned();
+        if wanted(&path_str) {
+            files.push(path_str);
+        }
+    }
+    Ok(())
+}
+#[cfg(all(test, feature = "python"))]
+mod tests {
+    use super::*;
+    #[test]
+    fn test_walk_skips_hidden_and_unknown_files() {
+        let root = std::env::temp_dir().join(format!("rmcm-walk-{}", std::process::id()));
+        fs::create_dir_all(root.join("pkg")).unwrap();
+        fs::create_dir_all(root.join(".git")).unwrap();
+        fs::write(root.join("pkg/b.py"), "").unwrap();
+        fs::write(root.join("a.py"), "").unwrap();
+        fs::write(root.join("notes.txt"), "").unwrap();
+        fs::write(root.join(".git/hook.py"), "").unwrap();
+        let files = collect_files(&[root.to_string_lossy().into_owned()], None).unwrap();
+        fs::remove_dir_all(&root).unwrap();
+        let names: Vec<String> = files
+            .iter()
+            .map(|f| f[root.to_string_lossy().len() + 1..].to_string())
+            .collect();
+        assert_eq!(names, vec!["a.py", "pkg/b.py"]);
+    }
+}